@@ -0,0 +1,340 @@
+//! A compact binary format for trees, with no dependencies.
+//!
+//! An encoded tree is made of:
+//! - a header: the format version as a byte, then the number of values as a varint,
+//! - the values in order, each encoded by its [`Codec`] knowing the value before it, which lets
+//!   integers only store the difference to it, as a varint,
+//! - a trailing checksum of everything before it, as 4 little-endian bytes.
+//!
+//! Varints are LEB128: 7 bits per byte, least significant first, with the high bit set on
+//! every byte but the last.
+
+use alloc::{string::String, vec::Vec};
+use core::{cmp::Ordering, convert::Infallible, fmt};
+
+use crate::{BST, Tree, compare::Compare, shared::Cloner, stats::Counters};
+
+/// The version of the format written in the header.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// A destination for encoded bytes.
+pub trait Sink {
+    /// The error writing to the sink can fail with.
+    type Error;
+
+    /// Writes all the given bytes to the sink.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl Sink for Vec<u8> {
+    type Error = Infallible;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<S> Sink for &mut S
+where
+    S: Sink + ?Sized,
+{
+    type Error = S::Error;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        (**self).write(bytes)
+    }
+}
+
+/// A value with a binary encoding.
+///
+/// Values are encoded in order, so each one is given the value encoded right before it, if
+/// any, to encode only what differs from it.
+pub trait Codec: Sized {
+    /// Encodes the value, knowing the previous one.
+    fn encode<S: Sink>(&self, prev: Option<&Self>, sink: &mut S) -> Result<(), S::Error>;
+
+    /// Decodes a value from the start of `input`, knowing the previous one, and advances
+    /// `input` past it.
+    fn decode(prev: Option<&Self>, input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// The ways decoding a tree can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of the tree.
+    Truncated,
+    /// The header has a format version this crate can't read.
+    UnsupportedVersion(u8),
+    /// Some bytes don't encode a valid value.
+    Invalid,
+    /// The checksum doesn't match the contents, which were corrupted.
+    ChecksumMismatch,
+    /// The values aren't sorted in strictly increasing order.
+    Unsorted,
+    /// There are bytes left after the checksum.
+    TrailingData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "input ended unexpectedly"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported format version {version}"),
+            Self::Invalid => write!(f, "invalid encoded value"),
+            Self::ChecksumMismatch => write!(f, "checksum doesn't match the contents"),
+            Self::Unsorted => write!(f, "values are not sorted"),
+            Self::TrailingData => write!(f, "unexpected data after the checksum"),
+        }
+    }
+}
+
+/// Writes an integer as a varint.
+pub fn write_varint<S: Sink>(mut n: u128, sink: &mut S) -> Result<(), S::Error> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            return sink.write(&[byte]);
+        }
+        sink.write(&[byte | 0x80])?;
+    }
+}
+
+/// Reads a varint from the start of `input`, and advances `input` past it.
+pub fn read_varint(input: &mut &[u8]) -> Result<u128, DecodeError> {
+    let mut n: u128 = 0;
+    for shift in (0..u128::BITS).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or(DecodeError::Truncated)?;
+        *input = rest;
+        let bits = u128::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            return Err(DecodeError::Invalid);
+        }
+        n |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(DecodeError::Invalid)
+}
+
+/// Helper function to take the next `len` bytes of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::Truncated);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+/// Integers store the difference to the previous value, zigzag encoded so that small
+/// differences either way take few bytes.
+macro_rules! impl_codec_int {
+    ($($int:ty => $signed:ty),* $(,)?) => {$(
+        impl Codec for $int {
+            fn encode<S: Sink>(&self, prev: Option<&Self>, sink: &mut S) -> Result<(), S::Error> {
+                let diff = self.wrapping_sub(prev.copied().unwrap_or(0)) as $signed;
+                let zigzag = (diff << 1) ^ (diff >> (<$signed>::BITS - 1));
+                write_varint(zigzag as <$signed as ZigZag>::Unsigned as u128, sink)
+            }
+
+            fn decode(prev: Option<&Self>, input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let zigzag = <$signed as ZigZag>::Unsigned::try_from(read_varint(input)?)
+                    .map_err(|_| DecodeError::Invalid)?;
+                let diff = (zigzag >> 1) as $signed ^ -((zigzag & 1) as $signed);
+                Ok(prev.copied().unwrap_or(0).wrapping_add(diff as $int))
+            }
+        }
+    )*};
+}
+
+/// Helper trait to name the unsigned twin of a signed integer.
+trait ZigZag {
+    type Unsigned;
+}
+
+macro_rules! impl_zigzag {
+    ($($signed:ty => $unsigned:ty),* $(,)?) => {$(
+        impl ZigZag for $signed {
+            type Unsigned = $unsigned;
+        }
+    )*};
+}
+
+impl_zigzag!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+impl_codec_int!(
+    u8 => i8, u16 => i16, u32 => i32, u64 => i64, u128 => i128, usize => isize,
+    i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128, isize => isize,
+);
+
+impl Codec for bool {
+    fn encode<S: Sink>(&self, _: Option<&Self>, sink: &mut S) -> Result<(), S::Error> {
+        sink.write(&[u8::from(*self)])
+    }
+
+    fn decode(_: Option<&Self>, input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match take(input, 1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+}
+
+impl Codec for char {
+    fn encode<S: Sink>(&self, prev: Option<&Self>, sink: &mut S) -> Result<(), S::Error> {
+        u32::from(*self).encode(prev.map(|&c| u32::from(c)).as_ref(), sink)
+    }
+
+    fn decode(prev: Option<&Self>, input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let code = u32::decode(prev.map(|&c| u32::from(c)).as_ref(), input)?;
+        char::from_u32(code).ok_or(DecodeError::Invalid)
+    }
+}
+
+/// Strings store their length as a varint, then their UTF-8 bytes.
+impl Codec for String {
+    fn encode<S: Sink>(&self, _: Option<&Self>, sink: &mut S) -> Result<(), S::Error> {
+        write_varint(self.len() as u128, sink)?;
+        sink.write(self.as_bytes())
+    }
+
+    fn decode(_: Option<&Self>, input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::try_from(read_varint(input)?).map_err(|_| DecodeError::Truncated)?;
+        let bytes = take(input, len)?;
+        let s = core::str::from_utf8(bytes).map_err(|_| DecodeError::Invalid)?;
+        Ok(String::from(s))
+    }
+}
+
+/// A sink computing the checksum of what is written through it.
+struct Checksummed<S> {
+    sink: S,
+    checksum: Checksum,
+}
+
+impl<S> Sink for Checksummed<S>
+where
+    S: Sink,
+{
+    type Error = S::Error;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.checksum.update(bytes);
+        self.sink.write(bytes)
+    }
+}
+
+/// The 32-bit FNV-1a hash.
+struct Checksum(u32);
+
+impl Checksum {
+    fn new() -> Self {
+        Self(0x811c_9dc5)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u32::from(byte)).wrapping_mul(0x0100_0193);
+        }
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Encodes the tree in the [binary format](crate::codec) into `sink`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<u32> = BST::from_sorted(vec![1000, 1001, 1005]);
+    /// let mut bytes = Vec::new();
+    /// tree.encode(&mut bytes).unwrap();
+    /// // Version, count, 3 values (the first taking 2 bytes) and checksum
+    /// assert_eq!(bytes.len(), 1 + 1 + 4 + 4);
+    /// assert_eq!(BST::decode(&bytes), Ok(tree));
+    /// ```
+    pub fn encode<S: Sink>(&self, sink: S) -> Result<(), S::Error>
+    where
+        T: Codec,
+    {
+        let mut sink = Checksummed {
+            sink,
+            checksum: Checksum::new(),
+        };
+        sink.write(&[FORMAT_VERSION])?;
+        write_varint(self.count_nodes() as u128, &mut sink)?;
+        let mut prev = None;
+        for value in self {
+            value.encode(prev, &mut sink)?;
+            prev = Some(value);
+        }
+        let checksum = sink.checksum.0;
+        sink.sink.write(&checksum.to_le_bytes())
+    }
+
+    /// Decodes a tree encoded by [`BST::encode`], and builds it in linear time.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if `bytes` don't hold exactly one valid encoded tree, sorted
+    /// by the comparator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, codec::DecodeError};
+    ///
+    /// let tree: BST<String> = BST::from_sorted(vec!["a".into(), "b".into()]);
+    /// let mut bytes = Vec::new();
+    /// tree.encode(&mut bytes).unwrap();
+    ///
+    /// assert_eq!(BST::<String>::decode(&bytes[..5]), Err(DecodeError::Truncated));
+    /// bytes[3] = b'c';
+    /// assert_eq!(BST::<String>::decode(&bytes), Err(DecodeError::ChecksumMismatch));
+    /// ```
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError>
+    where
+        T: Codec,
+        C: Compare<T> + Default,
+    {
+        let mut input = bytes;
+        let version = *take(&mut input, 1)?.first().expect("1 byte was taken");
+        if version != FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let count = usize::try_from(read_varint(&mut input)?).map_err(|_| DecodeError::Invalid)?;
+        // Every value takes at least a byte, don't let the count allocate more than that
+        let mut values: Vec<T> = Vec::with_capacity(count.min(input.len()));
+        for _ in 0..count {
+            let value = T::decode(values.last(), &mut input)?;
+            values.push(value);
+        }
+
+        let contents = bytes.len() - input.len();
+        let checksum = take(&mut input, 4)?;
+        let mut expected = Checksum::new();
+        expected.update(&bytes[..contents]);
+        if checksum != expected.0.to_le_bytes() {
+            return Err(DecodeError::ChecksumMismatch);
+        }
+        if !input.is_empty() {
+            return Err(DecodeError::TrailingData);
+        }
+
+        let cmp = C::default();
+        if !values.is_sorted_by(|a, b| cmp.compare(a, b) == Ordering::Less) {
+            return Err(DecodeError::Unsorted);
+        }
+        Ok(Self {
+            root: Tree::from_sorted(values.len(), &mut values.into_iter()),
+            cmp,
+            stats: Counters::default(),
+            cloner: Cloner::default(),
+        })
+    }
+}
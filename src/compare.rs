@@ -0,0 +1,108 @@
+use core::{borrow::Borrow, cmp::Ordering, marker::PhantomData};
+
+/// An ordering over values of type `T`, used by every ordered operation of a [`BST`](crate::BST).
+///
+/// Any `Fn(&T, &T) -> Ordering` closure is a comparator.
+///
+/// # Examples
+///
+/// ```
+/// use bst::BST;
+///
+/// let mut tree = BST::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+/// tree.insert(1);
+/// tree.insert(2);
+/// tree.insert(3);
+/// assert_eq!(tree.root_value(), Some(&2));
+/// assert_eq!(tree.root().left().and_then(|t| t.root_value()), Some(&3));
+/// ```
+pub trait Compare<T: ?Sized> {
+    /// Compares two values.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// The comparator ordering values by their [`Ord`] implementation.
+///
+/// This is the default comparator of a [`BST`](crate::BST).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Natural;
+
+impl<T> Compare<T> for Natural
+where
+    T: Ord + ?Sized,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// The comparator ordering values by the [`Ord`] implementation of a key projected out of them.
+///
+/// Built by [`BST::by_key`](crate::BST::by_key).
+#[derive(Clone, Copy)]
+pub struct ByKey<F>(F);
+
+impl<F> ByKey<F> {
+    /// Creates a comparator ordering values by the key `f` returns for them.
+    pub fn new<T, K>(f: F) -> Self
+    where
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        Self(f)
+    }
+}
+
+impl<T, K, F> Compare<T> for ByKey<F>
+where
+    F: Fn(&T) -> K,
+    K: Ord,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+impl<T, F> Compare<T> for F
+where
+    T: ?Sized,
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// The comparator ordering entries by the key they borrow as, with a comparator for the keys.
+///
+/// This lets collections built on a [`BST`](crate::BST) of entries look them up by key.
+pub(crate) struct KeyOrder<C, K: ?Sized>(pub(crate) C, PhantomData<fn(&K)>);
+
+impl<C, K: ?Sized> KeyOrder<C, K> {
+    pub(crate) fn new(cmp: C) -> Self {
+        Self(cmp, PhantomData)
+    }
+}
+
+impl<C, K, E> Compare<E> for KeyOrder<C, K>
+where
+    C: Compare<K>,
+    K: ?Sized,
+    E: Borrow<K> + ?Sized,
+{
+    fn compare(&self, a: &E, b: &E) -> Ordering {
+        self.0.compare(a.borrow(), b.borrow())
+    }
+}
+
+impl<C: Clone, K: ?Sized> Clone for KeyOrder<C, K> {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+impl<C: Default, K: ?Sized> Default for KeyOrder<C, K> {
+    fn default() -> Self {
+        Self::new(C::default())
+    }
+}
@@ -0,0 +1,172 @@
+use core::{borrow::Borrow, fmt};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::{
+    BST,
+    compare::{Compare, Natural},
+};
+
+/// A [`BST`] shared between threads, where any number of readers run at once.
+///
+/// Readers work on the latest published version of the tree, which they get by cloning a
+/// pointer under a lock held only for that. Writers take turns changing their own copy of the
+/// tree, which shares all its untouched nodes with the published one, then publish it as the
+/// new version. Readers thus never wait for a change to be made, only for it to be published.
+///
+/// # Examples
+///
+/// ```
+/// use bst::ConcurrentBST;
+/// use std::{sync::Arc, thread};
+///
+/// let tree = Arc::new(ConcurrentBST::new());
+/// let handles: Vec<_> = (0..4)
+///     .map(|t| {
+///         let tree = Arc::clone(&tree);
+///         thread::spawn(move || {
+///             for i in 0..10 {
+///                 tree.insert(t * 10 + i);
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(tree.count_nodes(), 40);
+/// assert!(tree.snapshot().iter().copied().eq(0..40));
+/// ```
+pub struct ConcurrentBST<T, C = Natural> {
+    /// The latest published version.
+    current: RwLock<Arc<BST<T, C>>>,
+    /// Held by the writer currently changing the tree.
+    writer: Mutex<()>,
+}
+
+impl<T, C> Default for ConcurrentBST<T, C>
+where
+    C: Default,
+{
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T> ConcurrentBST<T> {
+    /// Creates an empty tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, C> ConcurrentBST<T, C> {
+    /// Creates an empty tree ordering its values with the given comparator.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(BST::with_comparator(cmp))),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the latest published version of the tree.
+    ///
+    /// The version doesn't change as the tree does, so a reader can make several queries, or
+    /// iterate over it, and see a consistent state.
+    pub fn snapshot(&self) -> Arc<BST<T, C>> {
+        // A writer can't panic while holding this lock, but be resilient anyway: the version
+        // behind it is never left half changed
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Checks if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().is_empty()
+    }
+
+    /// Counts the number of values in the tree.
+    pub fn count_nodes(&self) -> usize {
+        self.snapshot().count_nodes()
+    }
+
+    /// Returns a copy of the value in the tree equal to the given one, if any.
+    pub fn get<Q>(&self, val: &Q) -> Option<T>
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.snapshot().get(val).cloned()
+    }
+
+    /// Checks if the tree contains a value.
+    pub fn contains<Q>(&self, val: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.snapshot().contains(val)
+    }
+
+    /// Inserts a value into the tree, and publishes the new version.
+    pub fn insert(&self, val: T)
+    where
+        T: Clone,
+        C: Clone + Compare<T>,
+    {
+        self.update(|tree| tree.insert(val));
+    }
+
+    /// Removes a value from the tree, and publishes the new version.
+    pub fn remove<Q>(&self, val: &Q)
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Clone + Compare<Q> + Compare<T>,
+    {
+        self.update(|tree| tree.remove(val));
+    }
+
+    /// Makes any changes to a copy of the latest version of the tree, and publishes it as the
+    /// new version.
+    ///
+    /// Writers take turns, so `f` sees every change published before. If it panics, nothing
+    /// is published.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::ConcurrentBST;
+    ///
+    /// let tree = ConcurrentBST::new();
+    /// let added = tree.update(|tree| {
+    ///     tree.insert(1);
+    ///     tree.insert(2);
+    ///     tree.count_nodes()
+    /// });
+    /// assert_eq!(added, 2);
+    /// ```
+    pub fn update<R, F>(&self, f: F) -> R
+    where
+        T: Clone,
+        C: Clone,
+        F: FnOnce(&mut BST<T, C>) -> R,
+    {
+        // A writer panicking while holding the lock publishes nothing, so the next one can
+        // carry on from the latest version
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut tree = BST::clone(&self.snapshot());
+        let res = f(&mut tree);
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(tree);
+        res
+    }
+}
+
+impl<T, C> fmt::Debug for ConcurrentBST<T, C>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.snapshot().fmt(f)
+    }
+}
@@ -0,0 +1,220 @@
+use alloc::string::String;
+use core::{
+    cmp::Ordering,
+    fmt::{self, Write},
+};
+
+use crate::{BST, Tree, compare::Compare};
+
+/// Options for the Graphviz export of a tree, to highlight some of its nodes.
+///
+/// Used by [`BST::to_dot_with`] and [`BST::write_dot_with`].
+pub struct DotOptions<'a, T> {
+    path: Option<&'a T>,
+    values: &'a [T],
+}
+
+impl<T> Default for DotOptions<'_, T> {
+    fn default() -> Self {
+        Self {
+            path: None,
+            values: &[],
+        }
+    }
+}
+
+impl<'a, T> DotOptions<'a, T> {
+    /// Creates options highlighting nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights the path a search for `target` takes from the root, nodes and edges.
+    pub fn highlight_path(mut self, target: &'a T) -> Self {
+        self.path = Some(target);
+        self
+    }
+
+    /// Highlights the nodes holding any of the given values.
+    pub fn highlight_values(mut self, values: &'a [T]) -> Self {
+        self.values = values;
+        self
+    }
+}
+
+/// Helper struct to write the nodes of a tree, numbering them as they come.
+struct DotWriter<'a, 'o, T, C, W> {
+    out: &'o mut W,
+    cmp: &'a C,
+    options: &'o DotOptions<'a, T>,
+    next_id: usize,
+}
+
+impl<T, C, W> DotWriter<'_, '_, T, C, W>
+where
+    T: fmt::Display,
+    C: Compare<T>,
+    W: Write,
+{
+    /// Writes a node and its subtrees, returning its id.
+    fn write_node(&mut self, tree: &Tree<T>, on_path: bool) -> Result<usize, fmt::Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let Tree::Node {
+            left,
+            value,
+            right,
+            depth,
+            ..
+        } = tree
+        else {
+            // Placeholder keeping a lone child on its side
+            writeln!(self.out, "    n{id} [style=invis, label=\"\"];")?;
+            return Ok(id);
+        };
+
+        write!(self.out, "    n{id} [label=\"")?;
+        write_escaped(self.out, value)?;
+        write!(self.out, "\\nh={depth} bf={}\"", tree.balance_factor())?;
+        if self
+            .options
+            .values
+            .iter()
+            .any(|v| self.cmp.compare(v, value) == Ordering::Equal)
+        {
+            write!(self.out, ", style=filled, fillcolor=lightblue")?;
+        }
+        if on_path {
+            write!(self.out, ", color=red, penwidth=2")?;
+        }
+        writeln!(self.out, "];")?;
+
+        let next = self
+            .options
+            .path
+            .filter(|_| on_path)
+            .map(|target| self.cmp.compare(target, value));
+        if left.is_empty() && right.is_empty() {
+            return Ok(id);
+        }
+        for (child, side) in [(left, Ordering::Less), (right, Ordering::Greater)] {
+            let child_on_path = next == Some(side) && !child.is_empty();
+            let child_id = self.write_node(child, child_on_path)?;
+            write!(self.out, "    n{id} -> n{child_id}")?;
+            if child.is_empty() {
+                write!(self.out, " [style=invis]")?;
+            } else if child_on_path {
+                write!(self.out, " [color=red, penwidth=2]")?;
+            }
+            writeln!(self.out, ";")?;
+        }
+        Ok(id)
+    }
+}
+
+/// Helper function to write a value inside a quoted label.
+fn write_escaped<W: Write, T: fmt::Display>(out: &mut W, value: &T) -> fmt::Result {
+    struct Escaper<'a, W>(&'a mut W);
+
+    impl<W: Write> Write for Escaper<'_, W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for c in s.chars() {
+                match c {
+                    '"' | '\\' => write!(self.0, "\\{c}")?,
+                    '\n' => self.0.write_str("\\n")?,
+                    c => self.0.write_char(c)?,
+                }
+            }
+            Ok(())
+        }
+    }
+
+    write!(Escaper(out), "{value}")
+}
+
+impl<T, C> BST<T, C> {
+    /// Exports the structure of the tree as a Graphviz digraph.
+    ///
+    /// Each node is labelled with its value, its height and its balance factor. Invisible
+    /// placeholders stand for missing children next to an existing one, so that the layout
+    /// keeps left children on the left and right ones on the right.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted(vec![1, 2]);
+    /// assert_eq!(
+    ///     tree.to_dot(),
+    ///     "digraph BST {
+    ///     graph [ordering=out];
+    ///     n0 [label=\"2\\nh=2 bf=1\"];
+    ///     n1 [label=\"1\\nh=1 bf=0\"];
+    ///     n0 -> n1;
+    ///     n2 [style=invis, label=\"\"];
+    ///     n0 -> n2 [style=invis];
+    /// }
+    /// "
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String
+    where
+        T: fmt::Display,
+        C: Compare<T>,
+    {
+        self.to_dot_with(&DotOptions::new())
+    }
+
+    /// Same as [`BST::to_dot`], with options to highlight some nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, dot::DotOptions};
+    ///
+    /// let tree: BST<_> = BST::from_sorted((1..=7).collect());
+    /// let dot = tree.to_dot_with(&DotOptions::new().highlight_path(&3).highlight_values(&[7]));
+    /// assert!(dot.contains("n0 -> n1 [color=red, penwidth=2];"));
+    /// assert!(dot.contains("label=\"7\\nh=1 bf=0\", style=filled"));
+    /// ```
+    pub fn to_dot_with(&self, options: &DotOptions<'_, T>) -> String
+    where
+        T: fmt::Display,
+        C: Compare<T>,
+    {
+        let mut out = String::new();
+        self.write_dot_with(&mut out, options)
+            .expect("writing to a string can't fail");
+        out
+    }
+
+    /// Same as [`BST::to_dot`], but writes the digraph to `out`.
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> fmt::Result
+    where
+        T: fmt::Display,
+        C: Compare<T>,
+    {
+        self.write_dot_with(out, &DotOptions::new())
+    }
+
+    /// Same as [`BST::to_dot_with`], but writes the digraph to `out`.
+    pub fn write_dot_with<W: Write>(&self, out: &mut W, options: &DotOptions<'_, T>) -> fmt::Result
+    where
+        T: fmt::Display,
+        C: Compare<T>,
+    {
+        writeln!(out, "digraph BST {{")?;
+        writeln!(out, "    graph [ordering=out];")?;
+        if !self.root.is_empty() {
+            let mut writer = DotWriter {
+                out: &mut *out,
+                cmp: &self.cmp,
+                options,
+                next_id: 0,
+            };
+            writer.write_node(&self.root, options.path.is_some())?;
+        }
+        writeln!(out, "}}")
+    }
+}
@@ -0,0 +1,136 @@
+//! The memory trees take on the heap.
+//!
+//! Every node holds its children behind their own allocation, even empty ones, so a tree of
+//! `n` values makes `2 * n` allocations, each holding a subtree and its reference counts. The
+//! root is stored in the tree itself.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{alloc::Layout, mem::size_of};
+
+use crate::{BST, Tree};
+
+/// A value owning memory on the heap, which the trees holding it add to their own usage.
+///
+/// # Examples
+///
+/// ```
+/// use bst::{BST, HeapSize};
+///
+/// let mut tree = BST::new();
+/// tree.insert(String::with_capacity(100));
+/// assert_eq!(tree.total_heap_size_bytes(), tree.heap_size_bytes() + 100);
+/// ```
+pub trait HeapSize {
+    /// Returns how many bytes the value owns on the heap, not counting its own size.
+    fn heap_size(&self) -> usize;
+}
+
+/// Values owning nothing on the heap.
+macro_rules! impl_heap_size_zero {
+    ($($ty:ty),* $(,)?) => {$(
+        impl HeapSize for $ty {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        }
+    )*};
+}
+
+impl_heap_size_zero!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + T::heap_size(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+/// A tree owns its nodes and what its values own.
+impl<T: HeapSize, C> HeapSize for BST<T, C> {
+    fn heap_size(&self) -> usize {
+        self.total_heap_size_bytes()
+    }
+}
+
+/// Helper function to compute the size of the allocation holding a subtree.
+fn allocation_size<T>() -> usize {
+    // An `Arc` or `Rc` allocates its strong and weak counts before the value
+    Layout::new::<[usize; 2]>()
+        .extend(Layout::new::<Tree<T>>())
+        .expect("a node is far smaller than the address space")
+        .0
+        .pad_to_align()
+        .size()
+}
+
+impl<T, C> BST<T, C> {
+    /// Returns how many bytes the nodes of the tree take on the heap, counting the allocations
+    /// of empty children too.
+    ///
+    /// Nodes shared with clones of the tree are counted in full, and what values own on the
+    /// heap is left out; [`BST::total_heap_size_bytes`] adds it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, Tree};
+    /// use std::mem::size_of;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((0..10u64).collect());
+    /// // 20 children, each behind its reference counts
+    /// let allocation = 2 * size_of::<usize>() + size_of::<Tree<u64>>();
+    /// assert_eq!(tree.heap_size_bytes(), 20 * allocation);
+    /// ```
+    pub fn heap_size_bytes(&self) -> usize {
+        2 * self.count_nodes() * allocation_size::<T>()
+    }
+
+    /// Same as [`BST::heap_size_bytes`], adding what the values own on the heap.
+    pub fn total_heap_size_bytes(&self) -> usize
+    where
+        T: HeapSize,
+    {
+        self.heap_size_bytes() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
@@ -0,0 +1,322 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    borrow::Borrow,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{
+    Tree,
+    compare::Compare,
+    shared::{DefaultKind, SharedKind},
+};
+
+/// An iterator over the values of a [`BST`](crate::BST), in order.
+///
+/// Created by [`BST::iter`](crate::BST::iter) and
+/// [`PersistentBST::iter`](crate::PersistentBST::iter).
+pub struct Iter<'a, T, K: SharedKind = DefaultKind, A = ()> {
+    /// The nodes whose value and right subtree are still to be visited, the next one last.
+    stack: Vec<&'a Tree<T, K, A>>,
+}
+
+impl<'a, T, K: SharedKind, A> Iter<'a, T, K, A> {
+    pub(crate) fn new(root: &'a Tree<T, K, A>) -> Self {
+        let mut iter = Self { stack: Vec::new() };
+        iter.push_left_spine(root);
+        iter
+    }
+
+    /// Pushes `node` and all its left descendants on the stack.
+    fn push_left_spine(&mut self, mut node: &'a Tree<T, K, A>) {
+        while let Tree::Node { left, .. } = node {
+            self.stack.push(node);
+            node = left;
+        }
+    }
+}
+
+impl<'a, T, K: SharedKind, A> Iterator for Iter<'a, T, K, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stack.pop()? {
+            Tree::Node { value, right, .. } => {
+                self.push_left_spine(right);
+                Some(value)
+            }
+            Tree::Empty => None,
+        }
+    }
+}
+
+impl<T, K: SharedKind, A> Clone for Iter<'_, T, K, A> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a [`BST`](crate::BST) within a range, in order.
+///
+/// Created by [`BST::range`](crate::BST::range).
+pub struct Range<'a, T, A = ()> {
+    inner: Iter<'a, T, DefaultKind, A>,
+    /// The last value in the range, or [`None`] once it has been yielded.
+    last: Option<&'a T>,
+}
+
+impl<'a, T, A> Range<'a, T, A> {
+    pub(crate) fn new<Q, C>(
+        root: &'a Tree<T, DefaultKind, A>,
+        range: impl RangeBounds<Q>,
+        cmp: &C,
+    ) -> Self
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let after_start = |value: &T| match range.start_bound() {
+            Bound::Included(start) => cmp.compare(value.borrow(), start).is_ge(),
+            Bound::Excluded(start) => cmp.compare(value.borrow(), start).is_gt(),
+            Bound::Unbounded => true,
+        };
+        let before_end = |value: &T| match range.end_bound() {
+            Bound::Included(end) => cmp.compare(value.borrow(), end).is_le(),
+            Bound::Excluded(end) => cmp.compare(value.borrow(), end).is_lt(),
+            Bound::Unbounded => true,
+        };
+
+        // Stack up the nodes an in-order walk would still visit from the start of the range
+        let mut inner = Iter { stack: Vec::new() };
+        let mut node = root;
+        while let Tree::Node {
+            left, value, right, ..
+        } = node
+        {
+            if after_start(value) {
+                inner.stack.push(node);
+                node = left;
+            } else {
+                node = right;
+            }
+        }
+
+        let mut last = None;
+        let mut node = root;
+        while let Tree::Node {
+            left, value, right, ..
+        } = node
+        {
+            if before_end(value) {
+                last = Some(value);
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+
+        // The range is empty if it ends before its first value
+        let first = inner.stack.last().and_then(|node| node.root_value());
+        if first
+            .zip(last)
+            .is_none_or(|(first, last)| !before_end(first) || !after_start(last))
+        {
+            inner.stack.clear();
+            last = None;
+        }
+
+        Self { inner, last }
+    }
+}
+
+impl<'a, T, A> Iterator for Range<'a, T, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.last?;
+        let value = self.inner.next()?;
+        if core::ptr::eq(value, last) {
+            self.last = None;
+        }
+        Some(value)
+    }
+}
+
+impl<T, A> Clone for Range<'_, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            last: self.last,
+        }
+    }
+}
+
+/// An iterator over the values of a [`BST`](crate::BST), level by level from the root, each
+/// level from left to right.
+///
+/// Created by [`BST::iter_level_order`](crate::BST::iter_level_order).
+pub struct LevelOrder<'a, T, A = ()> {
+    /// The nodes to visit, with their level, the next one first.
+    queue: VecDeque<(&'a Tree<T, DefaultKind, A>, usize)>,
+}
+
+impl<'a, T, A> LevelOrder<'a, T, A> {
+    pub(crate) fn new(root: &'a Tree<T, DefaultKind, A>) -> Self {
+        let mut queue = VecDeque::new();
+        if !root.is_empty() {
+            queue.push_back((root, 0));
+        }
+        Self { queue }
+    }
+
+    /// Yields the level of each value along with it, the root being at level 0.
+    pub fn with_levels(self) -> WithLevels<'a, T, A> {
+        WithLevels { inner: self }
+    }
+
+    fn next_with_level(&mut self) -> Option<(usize, &'a T)> {
+        let (
+            Tree::Node {
+                left, value, right, ..
+            },
+            level,
+        ) = self.queue.pop_front()?
+        else {
+            return None;
+        };
+        for child in [left, right] {
+            if !child.is_empty() {
+                self.queue.push_back((child, level + 1));
+            }
+        }
+        Some((level, value))
+    }
+}
+
+impl<'a, T, A> Iterator for LevelOrder<'a, T, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_level().map(|(_, value)| value)
+    }
+}
+
+impl<T, A> Clone for LevelOrder<'_, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a [`BST`](crate::BST) and their levels, level by level from
+/// the root.
+///
+/// Created by [`LevelOrder::with_levels`].
+pub struct WithLevels<'a, T, A = ()> {
+    inner: LevelOrder<'a, T, A>,
+}
+
+impl<'a, T, A> Iterator for WithLevels<'a, T, A> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next_with_level()
+    }
+}
+
+impl<T, A> Clone for WithLevels<'_, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a [`BST`](crate::BST), each node before its left subtree,
+/// then its right one.
+///
+/// Created by [`BST::iter_preorder`](crate::BST::iter_preorder).
+pub struct Preorder<'a, T, A = ()> {
+    /// The subtrees still to visit, the next one last.
+    stack: Vec<&'a Tree<T, DefaultKind, A>>,
+}
+
+impl<'a, T, A> Preorder<'a, T, A> {
+    pub(crate) fn new(root: &'a Tree<T, DefaultKind, A>) -> Self {
+        Self {
+            stack: Vec::from([root]),
+        }
+    }
+}
+
+impl<'a, T, A> Iterator for Preorder<'a, T, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Tree::Node {
+                left, value, right, ..
+            } = self.stack.pop()?
+            {
+                self.stack.push(right);
+                self.stack.push(left);
+                return Some(value);
+            }
+        }
+    }
+}
+
+impl<T, A> Clone for Preorder<'_, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a [`BST`](crate::BST), each node after its left subtree,
+/// then its right one.
+///
+/// Created by [`BST::iter_postorder`](crate::BST::iter_postorder).
+pub struct Postorder<'a, T, A = ()> {
+    /// The subtrees still to visit, the next one last, with whether their children were
+    /// already stacked above them.
+    stack: Vec<(&'a Tree<T, DefaultKind, A>, bool)>,
+}
+
+impl<'a, T, A> Postorder<'a, T, A> {
+    pub(crate) fn new(root: &'a Tree<T, DefaultKind, A>) -> Self {
+        Self {
+            stack: Vec::from([(root, false)]),
+        }
+    }
+}
+
+impl<'a, T, A> Iterator for Postorder<'a, T, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                (Tree::Empty, _) => (),
+                (Tree::Node { value, .. }, true) => return Some(value),
+                (node @ Tree::Node { left, right, .. }, false) => {
+                    self.stack.push((node, true));
+                    self.stack.push((right, false));
+                    self.stack.push((left, false));
+                }
+            }
+        }
+    }
+}
+
+impl<T, A> Clone for Postorder<'_, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+        }
+    }
+}
@@ -1,25 +1,109 @@
 #![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
-use alloc::boxed::Box;
-use core::{cmp::max, fmt};
+#[cfg(feature = "std")]
+extern crate std;
+use alloc::vec::Vec;
+use core::{borrow::Borrow, cmp::Ordering, fmt, mem, ops::RangeBounds};
+
+pub mod codec;
+pub mod compare;
+#[cfg(feature = "std")]
+pub mod concurrent;
+pub mod dot;
+pub mod heap;
+pub mod iter;
+pub mod multimap;
+pub mod multiset;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod persistent;
+pub mod render;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod shape;
+#[cfg(feature = "std")]
+pub mod sharded;
+mod shared;
+mod stats;
+pub mod svg;
+pub mod trace;
+pub mod transaction;
+mod tree;
+pub mod versioned;
+pub mod visit;
+
+pub use codec::Codec;
+pub use compare::{ByKey, Compare, Natural};
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentBST;
+pub use heap::HeapSize;
+pub use iter::{Iter, LevelOrder, Postorder, Preorder, Range};
+pub use multimap::BSTMultiMap;
+pub use multiset::BSTMultiset;
+#[cfg(feature = "rayon")]
+pub use par::ParIter;
+pub use persistent::PersistentBST;
+pub use render::{Render, TreeView};
+#[cfg(feature = "std")]
+pub use sharded::ShardedBSTMap;
+pub use shared::Subtree;
+#[cfg(feature = "stats")]
+pub use stats::TreeStats;
+pub use svg::Recording;
+pub use transaction::Transaction;
+pub use tree::{Rotation, Side, Tree};
+pub use versioned::VersionedBST;
+pub use visit::Visitor;
+
+use shared::{Cloner, DefaultKind};
+use stats::Counters;
+use tree::Augment;
 
 /// A binary search tree (BST) data structure.
-pub enum BST<T> {
-    /// Represents an empty tree.
-    Empty,
-    /// Represents a BST node containing a value and optional left and right subtrees.
-    Node {
-        left: Box<BST<T>>,
-        value: T,
-        right: Box<BST<T>>,
-        depth: usize,
-    },
+///
+/// The tree is kept balanced (as an AVL tree), and its values are ordered by the comparator
+/// `C`, which defaults to their [`Ord`] implementation.
+///
+/// Subtrees are reference counted and shared between clones, so cloning a tree takes constant
+/// time: only the root value is cloned. Changing a tree copies the nodes on the way to the
+/// change that other clones still share, and only those, so values that aren't [`Clone`] can
+/// still be inserted and removed.
+///
+/// `A` is what each node caches about its subtree, which is nothing but for the trees a
+/// [`BSTMultiset`] is built on.
+///
+/// # Examples
+///
+/// ```
+/// use bst::BST;
+///
+/// let mut tree = BST::new();
+/// tree.insert(1);
+/// tree.insert(2);
+/// let snapshot = tree.clone();
+/// tree.insert(3);
+/// assert!(tree.contains(&3));
+/// assert!(!snapshot.contains(&3));
+/// ```
+pub struct BST<T, C = Natural, A = ()> {
+    root: Tree<T, DefaultKind, A>,
+    cmp: C,
+    stats: Counters,
+    cloner: Cloner<T>,
 }
 
-impl<T> Default for BST<T> {
+impl<T, C, A> Default for BST<T, C, A>
+where
+    C: Default,
+{
     fn default() -> Self {
-        Self::Empty
+        Self {
+            root: Tree::Empty,
+            cmp: C::default(),
+            stats: Counters::default(),
+            cloner: Cloner::default(),
+        }
     }
 }
 
@@ -28,6 +112,83 @@ impl<T> BST<T> {
     pub fn new() -> Self {
         Self::default()
     }
+}
+
+impl<T, F> BST<T, ByKey<F>> {
+    /// Creates an empty binary search tree ordering its values by the key `f` returns for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::by_key(|s: &String| s.to_lowercase());
+    /// tree.insert(String::from("b"));
+    /// tree.insert(String::from("A"));
+    /// tree.insert(String::from("a"));
+    /// assert_eq!(tree.count_nodes(), 2);
+    /// assert!(tree.contains(&String::from("B")));
+    /// ```
+    pub fn by_key<K>(f: F) -> Self
+    where
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        Self::with_comparator(ByKey::new(f))
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Creates an empty binary search tree ordering its values with the given comparator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// assert!(tree.contains(&2));
+    /// ```
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::empty(cmp)
+    }
+}
+
+impl<T, C, A: Augment<T>> BST<T, C, A> {
+    /// Helper function to create an empty tree, whatever its nodes cache.
+    pub(crate) fn empty(cmp: C) -> Self {
+        Self {
+            root: Tree::Empty,
+            cmp,
+            stats: Counters::default(),
+            cloner: Cloner::default(),
+        }
+    }
+
+    /// Returns a reference to the comparator ordering the tree.
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    /// Returns a reference to the root node of the tree, to inspect its structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// assert_eq!(tree.root().root_value(), Some(&2));
+    /// assert_eq!(tree.root().left().unwrap().root_value(), Some(&1));
+    /// ```
+    pub fn root(&self) -> &Tree<T, DefaultKind, A> {
+        &self.root
+    }
 
     /// Checks if the tree is empty.
     ///
@@ -40,7 +201,7 @@ impl<T> BST<T> {
     /// assert!(tree.is_empty());
     /// ```
     pub fn is_empty(&self) -> bool {
-        matches!(self, Self::Empty)
+        self.root.is_empty()
     }
 
     /// Clears the tree, removing all nodes.
@@ -56,7 +217,7 @@ impl<T> BST<T> {
     /// assert!(tree.is_empty());
     /// ```
     pub fn clear(&mut self) {
-        *self = Self::Empty
+        self.root = Tree::Empty
     }
 
     /// Returns a reference to the value of the root node, if the tree is not empty.
@@ -72,10 +233,7 @@ impl<T> BST<T> {
     /// assert_eq!(tree.root_value(), Some(&10));
     /// ```
     pub fn root_value(&self) -> Option<&T> {
-        match self {
-            Self::Empty => None,
-            Self::Node { value, .. } => Some(value),
-        }
+        self.root.root_value()
     }
 
     /// Counts the number of nodes in the tree.
@@ -92,14 +250,7 @@ impl<T> BST<T> {
     /// assert_eq!(tree.count_nodes(), 2);
     /// ```
     pub fn count_nodes(&self) -> usize {
-        match self {
-            Self::Empty => 0,
-            Self::Node { left, right, .. } => {
-                let left_count = left.count_nodes();
-                let right_count = right.count_nodes();
-                1 + left_count + right_count
-            }
-        }
+        self.root.count_nodes()
     }
 
     /// Computes the depth/height of the tree, including the root node.
@@ -117,46 +268,17 @@ impl<T> BST<T> {
     /// assert_eq!(tree.depth(), 2);
     /// ```
     pub fn depth(&self) -> usize {
-        match self {
-            Self::Empty => 0,
-            Self::Node { depth, .. } => *depth,
-        }
+        self.root.depth()
+    }
+
+    /// Helper function to get the balance factor of the tree.
+    ///
+    /// This is effectively `depth(left) - depth(right)`.
+    pub fn balance_factor(&self) -> isize {
+        self.root.balance_factor()
     }
 
-    // /// Inserts a value into the tree without balancing.
-    // ///
-    // /// If the value already exists, it will not be inserted again.
-    // fn insert_unbalanced(&mut self, val: T) -> &mut Self
-    // where
-    //     T: PartialEq + Ord,
-    // {
-    //     match self {
-    //         Self::Empty => {
-    //             *self = Self::Node {
-    //                 left: Box::new(Self::Empty),
-    //                 value: val,
-    //                 right: Box::new(Self::Empty),
-    //             };
-    //             return self;
-    //         }
-    //         Self::Node { left, value, right } => {
-    //             if *value == val {
-    //                 return self;
-    //             }
-    //             if val < *value {
-    //                 left.insert_unbalanced(val);
-    //                 return self;
-    //             } else {
-    //                 right.insert_unbalanced(val);
-    //                 return self;
-    //             }
-    //         }
-    //     }
-    // }
-
-    /// Searches for a value in the tree.
-    ///
-    /// Returns a reference to the found value, or [`None`] if it doesn't exist.
+    /// Returns an iterator over the values of the tree, in order.
     ///
     /// # Examples
     ///
@@ -164,272 +286,152 @@ impl<T> BST<T> {
     /// use bst::BST;
     ///
     /// let mut tree = BST::new();
-    /// tree.insert(5);
+    /// tree.insert(2);
     /// tree.insert(3);
-    /// assert_eq!(tree.find(&3).unwrap().root_value(), Some(&3));
-    /// assert!(tree.find(&999).is_none());
+    /// tree.insert(1);
+    /// assert!(tree.iter().eq(&[1, 2, 3]));
     /// ```
-    pub fn find(&self, val: &T) -> Option<&Self>
-    where
-        T: PartialEq + Ord,
-    {
-        match self {
-            Self::Empty => None,
-            Self::Node {
-                left,
-                value,
-                right,
-                depth: _,
-            } => {
-                if value == val {
-                    return Some(self);
-                } else if val < value {
-                    return left.find(val);
-                } else {
-                    right.find(val)
-                }
-            }
-        }
+    pub fn iter(&self) -> Iter<'_, T, DefaultKind, A> {
+        Iter::new(&self.root)
     }
 
-    /// Checks if a value exists in the tree.
+    /// Returns an iterator over the values of the tree, breadth first: level by level from the
+    /// root, each level from left to right.
     ///
     /// # Examples
     ///
     /// ```
     /// use bst::BST;
     ///
-    /// let mut tree = BST::new();
-    /// tree.insert(5);
-    /// assert!(tree.contains(&5));
-    /// assert!(!tree.contains(&10));
+    /// let tree: BST<_> = BST::from_sorted((1..=5).collect());
+    /// assert!(tree.iter_level_order().eq(&[3, 2, 5, 1, 4]));
+    /// assert!(tree.iter_level_order().with_levels().eq([(0, &3), (1, &2), (1, &5), (2, &1), (2, &4)]));
     /// ```
-    pub fn contains(&self, val: &T) -> bool
-    where
-        T: PartialEq + Ord,
-    {
-        self.find(val).is_some()
-    }
-
-    /// Helper function to get a reference to the left subtree of a node
-    #[cfg(test)]
-    fn left(&self) -> Option<&Self> {
-        match self {
-            Self::Node { left, .. } if !left.is_empty() => Some(left),
-            _ => None,
-        }
-    }
-
-    /// Helper function to get a reference to the right subtree of a node
-    #[cfg(test)]
-    fn right(&self) -> Option<&Self> {
-        match self {
-            Self::Node { right, .. } if !right.is_empty() => Some(right),
-            _ => None,
-        }
-    }
-
-    /// Helper function to get a reference to the node at the left end of the tree.
-    #[cfg(test)]
-    fn left_end(&self) -> Option<&Self> {
-        match self {
-            BST::Node { left, .. } if !left.is_empty() => left.left_end(),
-            BST::Node { .. } => Some(self),
-            BST::Empty => None,
-        }
+    pub fn iter_level_order(&self) -> LevelOrder<'_, T, A> {
+        LevelOrder::new(&self.root)
     }
 
-    /// Helper function to get a reference to the node at the right end of the tree.
-    #[cfg(test)]
-    fn right_end(&self) -> Option<&Self> {
-        match self {
-            BST::Node { right, .. } if !right.is_empty() => right.right_end(),
-            BST::Node { .. } => Some(self),
-            BST::Empty => None,
-        }
-    }
-
-    /// Helper function to get the balance factor of the tree.
+    /// Returns an iterator over the values of the tree in preorder: each node before its left
+    /// subtree, then its right one.
     ///
-    /// This is effectively `depth(left) - depth(right)`.
-    pub fn balance_factor(&self) -> isize {
-        match self {
-            Self::Empty => 0,
-            Self::Node { left, right, .. } => {
-                let left_depth = left.depth();
-                let right_depth = right.depth();
-                left_depth as isize - right_depth as isize
-            }
-        }
-    }
-
-    /// Helper to update the height of the current node based on its children.
-    fn update_depth(&mut self) {
-        if let Self::Node {
-            left, right, depth, ..
-        } = self
-        {
-            *depth = 1 + max(left.depth(), right.depth());
-        }
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((1..=5).collect());
+    /// assert!(tree.iter_preorder().eq(&[3, 2, 1, 5, 4]));
+    /// ```
+    pub fn iter_preorder(&self) -> Preorder<'_, T, A> {
+        Preorder::new(&self.root)
     }
 
-    /// Helper function to rotate the tree left.
-    fn rotate_left(&mut self) {
-        let (value, depth, left, right) = match core::mem::take(self) {
-            Self::Node {
-                value,
-                depth,
-                left,
-                right,
-            } => (value, depth, left, right),
-            other => {
-                *self = other;
-                return;
-            }
-        };
-
-        match *right {
-            Self::Node {
-                value: r_value,
-                left: r_left,
-                right: r_right,
-                depth: _,
-            } => {
-                let mut new_left = Self::Node {
-                    value,
-                    left,
-                    right: r_left,
-                    depth,
-                };
-                new_left.update_depth();
-
-                *self = Self::Node {
-                    value: r_value,
-                    left: Box::new(new_left),
-                    right: r_right,
-                    depth: 0, // Temporary
-                };
-                self.update_depth();
-            }
-            Self::Empty => {
-                // Cannot rotate, put back
-                *self = Self::Node {
-                    value,
-                    left,
-                    right,
-                    depth,
-                };
-            }
-        }
+    /// Returns an iterator over the values of the tree in postorder: each node after its left
+    /// subtree, then its right one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((1..=5).collect());
+    /// assert!(tree.iter_postorder().eq(&[1, 2, 4, 5, 3]));
+    /// ```
+    pub fn iter_postorder(&self) -> Postorder<'_, T, A> {
+        Postorder::new(&self.root)
     }
 
-    /// Helper function to rotate the tree right.
-    fn rotate_right(&mut self) {
-        let (value, depth, right, left) = match core::mem::take(self) {
-            Self::Node {
-                value,
-                depth,
-                left,
-                right,
-            } => (value, depth, right, left),
-            other => {
-                *self = other;
-                return;
-            }
-        };
-
-        match *left {
-            Self::Node {
-                value: l_value,
-                left: l_left,
-                right: l_right,
-                depth: _,
-            } => {
-                let mut new_right = Self::Node {
-                    value,
-                    left: l_right,
-                    right,
-                    depth,
-                };
-                new_right.update_depth();
-
-                *self = Self::Node {
-                    value: l_value,
-                    left: l_left,
-                    right: Box::new(new_right),
-                    depth: 0, // Temporary
-                };
-                self.update_depth();
-            }
-            Self::Empty => {
-                // Cannot rotate, put back
-                *self = Self::Node {
-                    value,
-                    left,
-                    right,
-                    depth,
-                };
-            }
-        }
+    /// Returns an iterator over the values of the tree within a range, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// for val in 0..10 {
+    ///     tree.insert(val);
+    /// }
+    /// assert!(tree.range(3..6).eq(&[3, 4, 5]));
+    /// assert!(tree.range(8..).eq(&[8, 9]));
+    /// assert_eq!(tree.range(6..3).next(), None);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, T, A>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        Range::new(&self.root, range, &self.cmp)
     }
 
-    /// Helper function to rotate the left subtree left, and then the whole tree right.
-    fn rotate_left_right(&mut self) {
-        match self {
-            Self::Empty => return,
-            Self::Node { left, .. } => {
-                if !left.is_empty() {
-                    left.rotate_left();
-                    self.rotate_right();
-                }
-            }
-        }
+    /// Searches for the subtree rooted at a value.
+    ///
+    /// Returns a reference to the node holding the value, or [`None`] if it doesn't exist.
+    /// This is meant for inspecting the structure around a value; use [`BST::get`] to
+    /// just look the value up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(5);
+    /// tree.insert(3);
+    /// assert_eq!(tree.find(&3).unwrap().root_value(), Some(&3));
+    /// assert!(tree.find(&999).is_none());
+    /// ```
+    pub fn find<Q>(&self, val: &Q) -> Option<&Tree<T, DefaultKind, A>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.root.find(val, &self.stats.counted(&self.cmp))
     }
 
-    /// Helper function to rotate the left subtree right, and then the whole tree left.
-    fn rotate_right_left(&mut self) {
-        match self {
-            Self::Empty => return,
-            Self::Node { right, .. } => {
-                if !right.is_empty() {
-                    right.rotate_right();
-                    self.rotate_left();
-                }
-            }
-        }
+    /// Returns a reference to the value in the tree equal to the given one, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(String::from("apple"));
+    /// assert_eq!(tree.get("apple").map(String::as_str), Some("apple"));
+    /// assert!(tree.get("pear").is_none());
+    /// ```
+    pub fn get<Q>(&self, val: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.find(val).and_then(Tree::root_value)
     }
 
-    /// Rebalances the whole tree after it has a `|balance_factor|` of 1 or more.
-    // FIX: slower than it needs to be: height/depth should be stored or something
-    fn rebalance(&mut self) {
-        loop {
-            let bf = self.balance_factor();
-
-            match self {
-                Self::Empty => return,
-                Self::Node { left, right, .. } => {
-                    if bf > 1 {
-                        if !left.is_empty() {
-                            if left.balance_factor() >= 0 {
-                                self.rotate_right();
-                            } else {
-                                self.rotate_left_right();
-                            }
-                        }
-                    } else if bf < -1 {
-                        if !right.is_empty() {
-                            if right.balance_factor() <= 0 {
-                                self.rotate_left();
-                            } else {
-                                self.rotate_right_left();
-                            }
-                        }
-                    } else {
-                        break;
-                    }
-                }
-            }
-        }
+    /// Checks if a value exists in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(5);
+    /// assert!(tree.contains(&5));
+    /// assert!(!tree.contains(&10));
+    /// ```
+    pub fn contains<Q>(&self, val: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.find(val).is_some()
     }
 
     /// Inserts a value into the tree, rebalancing it right away.
@@ -451,63 +453,82 @@ impl<T> BST<T> {
     /// ```
     pub fn insert(&mut self, val: T)
     where
-        T: Ord,
+        C: Compare<T>,
     {
-        match self {
-            Self::Empty => {
-                *self = Self::Node {
-                    left: Box::new(Self::Empty),
-                    value: val,
-                    right: Box::new(Self::Empty),
-                    depth: 1,
-                };
-            }
-            Self::Node {
-                left, value, right, ..
-            } => {
-                if *value == val {
-                    return;
-                }
-                if val < *value {
-                    left.insert(val);
-                    self.update_depth();
-                    self.rebalance();
-                    return;
-                } else {
-                    right.insert(val);
-                    self.update_depth();
-                    self.rebalance();
-                    return;
-                }
-            }
-        }
+        self.root.insert_tracked(
+            val,
+            &self.stats.counted(&self.cmp),
+            None,
+            self.cloner.get(),
+            &mut &self.stats,
+        );
+        self.debug_check_invariants();
+    }
 
-        self.update_depth();
-        self.rebalance();
+    /// Returns a reference to the value in the tree equal to the given one, inserting it first
+    /// if it is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// assert_eq!(tree.get_or_insert(5), &5);
+    /// assert_eq!(tree.get_or_insert(5), &5);
+    /// assert_eq!(tree.count_nodes(), 1);
+    /// ```
+    pub fn get_or_insert(&mut self, val: T) -> &T
+    where
+        C: Compare<T>,
+    {
+        let mut path = Vec::new();
+        self.root.insert_tracked(
+            val,
+            &self.stats.counted(&self.cmp),
+            Some(&mut path),
+            self.cloner.get(),
+            &mut &self.stats,
+        );
+        self.debug_check_invariants();
+        self.root
+            .value_at(path)
+            .expect("insertion path should lead to a value")
     }
 
-    /// Helper function to take ownership of the largest value in the tree.
-    fn take_max(&mut self) -> Option<T> {
-        match self {
-            Self::Empty => None,
-            Self::Node { right, .. } if !right.is_empty() => {
-                let val = right.take_max();
-                self.update_depth();
-                self.rebalance();
-                val
-            }
-            // This node is the max
-            Self::Node { .. } => {
-                let old_self = core::mem::take(self);
-                if let Self::Node { value, left, .. } = old_self {
-                    // Replace this node with its left child
-                    *self = *left;
-                    Some(value)
-                } else {
-                    unreachable!()
-                }
-            }
+    /// Returns a reference to the value in the tree equal to `val`, inserting the result of
+    /// `f(val)` first if it is missing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value returned by `f` is not equal to `val`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree: BST<String> = BST::new();
+    /// let interned = tree.get_or_insert_with("apple", str::to_owned);
+    /// assert_eq!(interned, "apple");
+    /// assert!(tree.contains("apple"));
+    /// ```
+    pub fn get_or_insert_with<Q, F>(&mut self, val: &Q, f: F) -> &T
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+        F: FnOnce(&Q) -> T,
+    {
+        if self.contains(val) {
+            return self.get(val).expect("value should be in the tree");
         }
+        let new = f(val);
+        assert!(
+            self.cmp.compare(new.borrow(), val) == Ordering::Equal,
+            "new value is not equal to the key"
+        );
+        self.get_or_insert(new)
     }
 
     /// Remove a value from the tree.
@@ -528,123 +549,355 @@ impl<T> BST<T> {
     /// tree.remove(&3);
     /// assert!(!tree.contains(&3));
     /// ```
-    pub fn remove(&mut self, val: &T)
+    pub fn remove<Q>(&mut self, val: &Q)
     where
-        T: Ord,
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
     {
-        match self {
-            Self::Empty => return,
-            Self::Node {
-                left, value, right, ..
-            } => {
-                if *val < *value {
-                    left.remove(val);
-                } else if *val > *value {
-                    right.remove(val);
-                } else {
-                    // Node found
-                    if left.is_empty() {
-                        *self = *core::mem::take(right);
-                    } else if right.is_empty() {
-                        *self = *core::mem::take(left);
-                    } else {
-                        // Two children
-                        if let Some(max) = left.take_max() {
-                            *value = max;
-                        }
-                    }
-                }
+        self.root.remove(
+            val,
+            &self.stats.counted(&self.cmp),
+            self.cloner.get(),
+            &mut &self.stats,
+        );
+        self.debug_check_invariants();
+    }
+
+    /// Modifies the value in the tree equal to `val` in place.
+    ///
+    /// If the change moves the value out of order relative to its neighbours, it is taken out
+    /// and inserted again, rebalancing the tree. Should it then be equal to another value of
+    /// the tree, it is dropped just like a duplicate passed to [`BST::insert`].
+    ///
+    /// Returns `false` if there was no value equal to `val` to modify. Otherwise, the nodes on
+    /// the way to the value that are shared with clones of the tree are copied first, even if
+    /// `f` leaves it unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(1);
+    /// tree.insert(5);
+    /// tree.insert(10);
+    /// assert!(tree.modify(&5, |v| *v = 20));
+    /// assert!(!tree.contains(&5));
+    /// assert!(tree.contains(&20));
+    /// assert!(!tree.modify(&5, |v| *v = 30));
+    /// ```
+    pub fn modify<Q, F>(&mut self, val: &Q, f: F) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+        F: FnOnce(&mut T),
+    {
+        let found = match self.root.modify(
+            val,
+            &self.stats.counted(&self.cmp),
+            f,
+            self.cloner.get(),
+            &mut &self.stats,
+        ) {
+            None => false,
+            Some(None) => true,
+            Some(Some(moved)) => {
+                self.root.insert_tracked(
+                    moved,
+                    &self.stats.counted(&self.cmp),
+                    None,
+                    self.cloner.get(),
+                    &mut &self.stats,
+                );
+                true
             }
+        };
+        self.debug_check_invariants();
+        found
+    }
+
+    /// Builds a balanced tree out of values sorted in strictly increasing order, in linear
+    /// time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values are not sorted, or if some are equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((0..7).collect());
+    /// assert_eq!(tree.depth(), 3);
+    /// assert_eq!(tree.root_value(), Some(&3));
+    /// ```
+    pub fn from_sorted(values: Vec<T>) -> Self
+    where
+        C: Compare<T> + Default,
+    {
+        let cmp = C::default();
+        assert!(
+            values.is_sorted_by(|a, b| cmp.compare(a, b) == Ordering::Less),
+            "values are not sorted"
+        );
+        Self {
+            root: Tree::from_sorted(values.len(), &mut values.into_iter()),
+            cmp,
+            stats: Counters::default(),
+            cloner: Cloner::default(),
         }
+    }
 
-        self.update_depth();
-        self.rebalance();
+    /// Merges the values of two trees into one, keeping the values of `self` when both have
+    /// equal ones.
+    ///
+    /// Both trees are split and joined back rather than having their values inserted one by
+    /// one, which takes `O(m log(n / m + 1))` time for trees of sizes `m` and `n`, `m <= n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let a: BST<_> = BST::from_sorted(vec![1, 3, 5]);
+    /// let b: BST<_> = BST::from_sorted(vec![2, 3, 4]);
+    /// assert!(a.union(b).iter().eq(&[1, 2, 3, 4, 5]));
+    /// ```
+    pub fn union(mut self, other: Self) -> Self
+    where
+        T: Clone,
+        C: Compare<T>,
+    {
+        let root = mem::take(&mut self.root).union(other.root, &self.stats.counted(&self.cmp));
+        // The result shares the nodes `other` shared with its clones
+        self.with_shared_root(root)
+    }
+
+    /// Keeps the values of `self` which `other` has equal ones to.
+    ///
+    /// Like [`BST::union`], this splits and joins the trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let a: BST<_> = BST::from_sorted(vec![1, 3, 5]);
+    /// let b: BST<_> = BST::from_sorted(vec![2, 3, 4, 5]);
+    /// assert!(a.intersection(b).iter().eq(&[3, 5]));
+    /// ```
+    pub fn intersection(mut self, other: Self) -> Self
+    where
+        T: Clone,
+        C: Compare<T>,
+    {
+        let root =
+            mem::take(&mut self.root).intersection(other.root, &self.stats.counted(&self.cmp));
+        self.with_shared_root(root)
+    }
+
+    /// Applies a batch of changes all at once, or not at all.
+    ///
+    /// `f` makes its changes through a [`Transaction`], which also reads them back. If it
+    /// returns `Err` or panics, the tree is left exactly as it was before. Taking the snapshot
+    /// to roll back to takes constant time, as it shares its nodes with the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(1);
+    ///
+    /// let res = tree.transaction(|txn| {
+    ///     txn.insert(2);
+    ///     txn.remove(&1);
+    ///     if txn.contains(&2) { Err("rolled back") } else { Ok(()) }
+    /// });
+    /// assert_eq!(res, Err("rolled back"));
+    /// assert!(tree.iter().eq(&[1]));
+    ///
+    /// let res: Result<_, ()> = tree.transaction(|txn| {
+    ///     txn.insert(3);
+    ///     Ok(txn.count_nodes())
+    /// });
+    /// assert_eq!(res, Ok(2));
+    /// assert!(tree.iter().eq(&[1, 3]));
+    /// ```
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        T: Clone,
+        F: FnOnce(&mut Transaction<'_, T, C, A>) -> Result<R, E>,
+    {
+        let mut txn = Transaction::new(self);
+        let res = f(&mut txn);
+        if res.is_ok() {
+            txn.commit();
+        }
+        res
+    }
+
+    /// Checks that the tree is ordered, balanced, and that what every node caches about its
+    /// subtree (its `depth`, and a multiset's size) is right.
+    ///
+    /// The tree's operations keep these invariants; a violation means a value's order was
+    /// changed behind the tree's back (e.g. through interior mutability).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// for val in 0..100 {
+    ///     tree.insert(val);
+    /// }
+    /// assert_eq!(tree.check_invariants(), Ok(()));
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), InvariantError>
+    where
+        C: Compare<T>,
+    {
+        self.root
+            .check_invariants(&self.cmp, None, None)
+            .map(|_| ())
+    }
+
+    /// Asserts the tree invariants after a mutation, when the `debug-invariants` feature is
+    /// enabled in a debug build.
+    #[inline]
+    fn debug_check_invariants(&self)
+    where
+        C: Compare<T>,
+    {
+        #[cfg(feature = "debug-invariants")]
+        debug_assert_eq!(self.check_invariants(), Ok(()), "tree invariants violated");
+    }
+
+    /// Helper to replace the root with one which may share nodes with other trees, recording
+    /// how to copy them first.
+    ///
+    /// Every tree made out of shared nodes must be built through here, or cloned.
+    pub(crate) fn with_shared_root(mut self, root: Tree<T, DefaultKind, A>) -> Self
+    where
+        T: Clone,
+    {
+        self.cloner.record();
+        self.root = root;
+        self
+    }
+
+    /// Helper to split the tree into the values ordered before `val`, and `val` with the
+    /// values ordered after it. Both trees share their nodes with this one, which is left as
+    /// it was.
+    #[cfg(feature = "std")]
+    pub(crate) fn split_shared(&self, val: T) -> (Self, Self)
+    where
+        T: Clone,
+        C: Compare<T> + Clone,
+    {
+        let (lower, _, upper) = self
+            .root
+            .shallow_copy(T::clone)
+            .split(&val, &self.stats.counted(&self.cmp));
+        let upper = Tree::join(Tree::Empty, val, upper);
+        (
+            Self::empty(self.cmp.clone()).with_shared_root(lower),
+            Self::empty(self.cmp.clone()).with_shared_root(upper),
+        )
+    }
+
+    /// Helper to join the tree with `other`, whose values are all ordered after its own. The
+    /// result shares its nodes with both trees, which are left as they were.
+    #[cfg(feature = "std")]
+    pub(crate) fn join_shared(&self, other: &Self) -> Self
+    where
+        T: Clone,
+        C: Clone,
+    {
+        let root = Tree::join_pair(
+            self.root.shallow_copy(T::clone),
+            other.root.shallow_copy(T::clone),
+        );
+        Self::empty(self.cmp.clone()).with_shared_root(root)
+    }
+}
+
+/// The invariant of a [`BST`] found broken by [`BST::check_invariants`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantError {
+    /// A value is not strictly between the values ordered before and after it.
+    Unordered,
+    /// The depths of a node's subtrees differ by more than one.
+    Unbalanced,
+    /// A node's cached `depth` doesn't match its subtrees.
+    WrongDepth,
+    /// A multiset node's cached size doesn't match its count and subtrees.
+    WrongSize,
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unordered => write!(f, "values are out of order"),
+            Self::Unbalanced => write!(f, "subtree depths differ by more than one"),
+            Self::WrongDepth => write!(f, "cached depth doesn't match the subtrees"),
+            Self::WrongSize => write!(f, "cached size doesn't match the count and subtrees"),
+        }
     }
 }
 
-impl<T> Clone for BST<T>
+/// Cloning a tree takes constant time, as the clone shares all the nodes below the root, only
+/// cloning the root value.
+impl<T, C, A> Clone for BST<T, C, A>
 where
     T: Clone,
+    C: Clone,
+    A: Augment<T>,
 {
     fn clone(&self) -> Self {
-        match self {
-            Self::Empty => Self::Empty,
-            Self::Node {
-                left,
-                value,
-                right,
-                depth,
-            } => Self::Node {
-                left: left.clone(),
-                value: value.clone(),
-                right: right.clone(),
-                depth: *depth,
-            },
+        self.cloner.record();
+        let cloner = Cloner::default();
+        cloner.record();
+        Self {
+            root: self.root.shallow_copy(T::clone),
+            cmp: self.cmp.clone(),
+            stats: self.stats.clone(),
+            cloner,
         }
     }
 }
 
-impl<T> PartialEq for BST<T>
+impl<'a, T, C, A: Augment<T>> IntoIterator for &'a BST<T, C, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, DefaultKind, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C, A> PartialEq for BST<T, C, A>
 where
     T: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Empty, Self::Empty) => true,
-            (
-                Self::Node {
-                    left: l1,
-                    value: v1,
-                    right: r1,
-                    ..
-                },
-                Self::Node {
-                    left: l2,
-                    value: v2,
-                    right: r2,
-                    ..
-                },
-            ) => v1 == v2 && l1 == l2 && r1 == r2,
-            _ => false,
-        }
+        self.root == other.root
     }
 }
 
-impl<T> fmt::Debug for BST<T>
+/// Prints the values in order, like a set. [`BST::tree_view`] prints the structure instead.
+impl<T, C, A: Augment<T>> fmt::Debug for BST<T, C, A>
 where
     T: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fn fmt_node<T: fmt::Debug>(
-            node: &BST<T>,
-            f: &mut fmt::Formatter,
-            depth: usize,
-        ) -> fmt::Result {
-            match node {
-                BST::Empty => Ok(()),
-                BST::Node {
-                    left,
-                    value,
-                    right,
-                    depth: node_depth,
-                } => {
-                    fmt_node(right, f, node_depth + 1)?;
-
-                    for _ in 0..depth {
-                        write!(f, "---")?;
-                    }
-
-                    // Display value and the cached depth [H:x]
-                    writeln!(f, "{:?} [{}]", value, node_depth)?;
-
-                    fmt_node(left, f, node_depth + 1)?;
-                    Ok(())
-                }
-            }
-        }
-
-        writeln!(f)?;
-        fmt_node(self, f, 0)
+        f.debug_set().entries(self.iter()).finish()
     }
 }
 
@@ -0,0 +1,299 @@
+use alloc::{vec, vec::Vec};
+use core::{borrow::Borrow, fmt, ops::RangeBounds};
+
+use crate::{
+    BST,
+    compare::{Compare, KeyOrder, Natural},
+};
+
+/// A key of a multimap, with the values mapped to it in insertion order.
+#[derive(Clone, PartialEq)]
+struct Entry<K, V> {
+    key: K,
+    values: Vec<V>,
+}
+
+impl<K, V> Borrow<K> for Entry<K, V> {
+    fn borrow(&self) -> &K {
+        &self.key
+    }
+}
+
+/// An ordered map where each key can map to many values.
+///
+/// Keys are ordered by the comparator `C`, just like in a [`BST`], which the multimap is built
+/// on. The values of a key are kept in insertion order.
+pub struct BSTMultiMap<K, V, C = Natural> {
+    tree: BST<Entry<K, V>, KeyOrder<C, K>>,
+    len: usize,
+}
+
+impl<K, V, C> Default for BSTMultiMap<K, V, C>
+where
+    C: Default,
+{
+    fn default() -> Self {
+        Self {
+            tree: BST::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<K, V> BSTMultiMap<K, V> {
+    /// Creates an empty multimap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, C> BSTMultiMap<K, V, C> {
+    /// Creates an empty multimap ordering its keys with the given comparator.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            tree: BST::with_comparator(KeyOrder::new(cmp)),
+            len: 0,
+        }
+    }
+
+    /// Returns the total number of `(key, value)` pairs in the multimap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiMap;
+    ///
+    /// let mut map = BSTMultiMap::new();
+    /// map.insert(1, "a");
+    /// map.insert(1, "b");
+    /// map.insert(2, "c");
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.key_count(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Counts the number of distinct keys in the multimap.
+    pub fn key_count(&self) -> usize {
+        self.tree.count_nodes()
+    }
+
+    /// Checks if the multimap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Clears the multimap, removing all pairs.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.len = 0;
+    }
+
+    /// Maps one more value to a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiMap;
+    ///
+    /// let mut map = BSTMultiMap::new();
+    /// map.insert(10, "start");
+    /// map.insert(10, "stop");
+    /// assert!(map.get_all(&10).eq(&["start", "stop"]));
+    /// ```
+    pub fn insert(&mut self, key: K, value: V)
+    where
+        C: Compare<K>,
+    {
+        let mut value = Some(value);
+        let pushed = self.tree.modify(&key, |entry| {
+            entry.values.extend(value.take());
+        });
+        if !pushed {
+            self.tree.insert(Entry {
+                key,
+                values: vec![value.take().expect("value should not have been pushed")],
+            });
+        }
+        self.len += 1;
+    }
+
+    /// Returns an iterator over the values mapped to a key, in insertion order.
+    pub fn get_all(&self, key: &K) -> core::slice::Iter<'_, V>
+    where
+        C: Compare<K>,
+    {
+        self.tree
+            .get(key)
+            .map_or(&[][..], |entry| &entry.values)
+            .iter()
+    }
+
+    /// Checks if at least one value is mapped to a key.
+    pub fn contains_key(&self, key: &K) -> bool
+    where
+        C: Compare<K>,
+    {
+        self.tree.contains(key)
+    }
+
+    /// Removes the most recently inserted value mapped to a key, returning it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiMap;
+    ///
+    /// let mut map = BSTMultiMap::new();
+    /// map.insert(10, "start");
+    /// map.insert(10, "stop");
+    /// assert_eq!(map.remove_one(&10), Some("stop"));
+    /// assert_eq!(map.remove_one(&10), Some("start"));
+    /// assert_eq!(map.remove_one(&10), None);
+    /// assert!(!map.contains_key(&10));
+    /// ```
+    pub fn remove_one(&mut self, key: &K) -> Option<V>
+    where
+        C: Compare<K>,
+    {
+        let mut removed = None;
+        let mut emptied = false;
+        self.tree.modify(key, |entry| {
+            removed = entry.values.pop();
+            emptied = entry.values.is_empty();
+        });
+        if emptied {
+            self.tree.remove(key);
+        }
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Removes a key and every value mapped to it, returning the values in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiMap;
+    ///
+    /// let mut map = BSTMultiMap::new();
+    /// map.insert(10, "start");
+    /// map.insert(10, "stop");
+    /// assert_eq!(map.remove_all(&10), ["start", "stop"]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_all(&mut self, key: &K) -> Vec<V>
+    where
+        C: Compare<K>,
+    {
+        let mut removed = Vec::new();
+        self.tree.modify(key, |entry| {
+            removed = core::mem::take(&mut entry.values);
+        });
+        if !removed.is_empty() {
+            self.tree.remove(key);
+            self.len -= removed.len();
+        }
+        removed
+    }
+
+    /// Returns an iterator over every `(key, value)` pair, in key order and then in insertion
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + Clone {
+        self.tree.iter().flat_map(Entry::pairs)
+    }
+
+    /// Returns an iterator over the `(key, value)` pairs with keys within a range, in key order
+    /// and then in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiMap;
+    ///
+    /// let mut map = BSTMultiMap::new();
+    /// map.insert(3, "c");
+    /// map.insert(1, "a");
+    /// map.insert(2, "b1");
+    /// map.insert(2, "b2");
+    /// assert!(map.range(2..).eq([(&2, &"b1"), (&2, &"b2"), (&3, &"c")]));
+    /// ```
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = (&K, &V)> + Clone
+    where
+        C: Compare<K>,
+        R: RangeBounds<K>,
+    {
+        self.tree.range(range).flat_map(Entry::pairs)
+    }
+
+    /// Returns an iterator over the distinct keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + Clone {
+        self.tree.iter().map(|entry| &entry.key)
+    }
+}
+
+impl<K, V> Entry<K, V> {
+    /// Pairs the key with each of its values.
+    fn pairs(&self) -> impl Iterator<Item = (&K, &V)> + Clone {
+        self.values.iter().map(|value| (&self.key, value))
+    }
+}
+
+impl<K, V, C> Clone for BSTMultiMap<K, V, C>
+where
+    K: Clone,
+    V: Clone,
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V, C> PartialEq for BSTMultiMap<K, V, C>
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V, C> fmt::Debug for BSTMultiMap<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, C> Extend<(K, V)> for BSTMultiMap<K, V, C>
+where
+    C: Compare<K>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for BSTMultiMap<K, V>
+where
+    K: Ord,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
@@ -0,0 +1,393 @@
+use core::{borrow::Borrow, fmt, iter};
+
+use crate::{
+    BST, Tree,
+    compare::{Compare, KeyOrder, Natural},
+    iter::Iter,
+    shared::DefaultKind,
+    tree::Augment,
+};
+
+/// A distinct value of the multiset, with how many times it occurs.
+#[derive(Clone)]
+struct Entry<T> {
+    value: T,
+    count: usize,
+}
+
+impl<T> Borrow<T> for Entry<T> {
+    fn borrow(&self) -> &T {
+        &self.value
+    }
+}
+
+/// The total count of the values in a subtree, cached on its root.
+#[derive(Clone, PartialEq)]
+struct Size(usize);
+
+impl Size {
+    /// Helper function to get the total count of the values in a tree.
+    fn of<T>(tree: &Tree<Entry<T>, DefaultKind, Size>) -> usize {
+        tree.aug().map_or(0, |size| size.0)
+    }
+}
+
+impl<T> Augment<Entry<T>> for Size {
+    fn compute(entry: &Entry<T>, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let size = |size: Option<&Self>| size.map_or(0, |size| size.0);
+        Size(size(left) + entry.count + size(right))
+    }
+}
+
+/// A sorted multiset (bag) keeping every duplicate of a value, as a count on a single node.
+///
+/// Values are ordered by the comparator `C`, just like in a [`BST`], which the multiset is
+/// built on. Each node also caches the total count of its subtree, which
+/// [`BSTMultiset::rank`] and [`BSTMultiset::select`] walk down with.
+pub struct BSTMultiset<T, C = Natural> {
+    tree: BST<Entry<T>, KeyOrder<C, T>, Size>,
+}
+
+impl<T, C> Default for BSTMultiset<T, C>
+where
+    C: Default,
+{
+    fn default() -> Self {
+        Self {
+            tree: BST::default(),
+        }
+    }
+}
+
+impl<T> BSTMultiset<T> {
+    /// Creates an empty multiset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, C> BSTMultiset<T, C> {
+    /// Creates an empty multiset ordering its values with the given comparator.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            tree: BST::empty(KeyOrder::new(cmp)),
+        }
+    }
+
+    /// Returns the number of values in the multiset, counting every duplicate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// set.insert(1);
+    /// set.insert(1);
+    /// set.insert(2);
+    /// assert_eq!(set.len(), 3);
+    /// assert_eq!(set.distinct_len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        Size::of(&self.tree.root)
+    }
+
+    /// Counts the number of distinct values in the multiset.
+    pub fn distinct_len(&self) -> usize {
+        self.tree.count_nodes()
+    }
+
+    /// Checks if the multiset is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Clears the multiset, removing all values.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Adds one occurrence of a value to the multiset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// set.insert(5);
+    /// set.insert(5);
+    /// assert_eq!(set.count(&5), 2);
+    /// ```
+    pub fn insert(&mut self, val: T)
+    where
+        C: Compare<T>,
+    {
+        if !self.tree.modify(&val, |entry| entry.count += 1) {
+            self.tree.insert(Entry {
+                value: val,
+                count: 1,
+            });
+        }
+    }
+
+    /// Returns the number of occurrences of a value in the multiset.
+    pub fn count(&self, val: &T) -> usize
+    where
+        C: Compare<T>,
+    {
+        self.tree.get(val).map_or(0, |entry| entry.count)
+    }
+
+    /// Checks if a value occurs in the multiset at least once.
+    pub fn contains(&self, val: &T) -> bool
+    where
+        C: Compare<T>,
+    {
+        self.tree.contains(val)
+    }
+
+    /// Removes one occurrence of a value from the multiset.
+    ///
+    /// Returns `false` if the value didn't occur in the multiset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// set.insert(5);
+    /// set.insert(5);
+    /// assert!(set.remove_one(&5));
+    /// assert_eq!(set.count(&5), 1);
+    /// assert!(set.remove_one(&5));
+    /// assert!(!set.remove_one(&5));
+    /// ```
+    pub fn remove_one(&mut self, val: &T) -> bool
+    where
+        C: Compare<T>,
+    {
+        match self.count(val) {
+            0 => return false,
+            1 => self.tree.remove(val),
+            _ => {
+                self.tree.modify(val, |entry| entry.count -= 1);
+            }
+        }
+        true
+    }
+
+    /// Removes every occurrence of a value from the multiset, returning how many there were.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// set.insert(5);
+    /// set.insert(5);
+    /// assert_eq!(set.remove_all(&5), 2);
+    /// assert!(!set.contains(&5));
+    /// ```
+    pub fn remove_all(&mut self, val: &T) -> usize
+    where
+        C: Compare<T>,
+    {
+        let count = self.count(val);
+        if count > 0 {
+            self.tree.remove(val);
+        }
+        count
+    }
+
+    /// Returns an iterator over the values of the multiset in order, repeating each one as many
+    /// times as it occurs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// for val in [3, 1, 3, 2, 1, 3] {
+    ///     set.insert(val);
+    /// }
+    /// assert!(set.iter().eq(&[1, 1, 2, 3, 3, 3]));
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = &T> + Clone {
+        self.iter_counts()
+            .flat_map(|(value, count)| iter::repeat_n(value, count))
+    }
+
+    /// Returns an iterator over the distinct values of the multiset in order, along with the
+    /// number of times each one occurs.
+    pub fn iter_counts(&self) -> IterCounts<'_, T> {
+        IterCounts {
+            inner: self.tree.iter(),
+        }
+    }
+
+    /// Counts the values of the multiset ordered strictly before `val`, duplicates included.
+    ///
+    /// This walks down the tree once, adding up the counts of the subtrees it passes, so it
+    /// takes `O(log n)` time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// for val in [1, 1, 2, 3] {
+    ///     set.insert(val);
+    /// }
+    /// assert_eq!(set.rank(&2), 2);
+    /// assert_eq!(set.rank(&3), 3);
+    /// assert_eq!(set.rank(&10), 4);
+    /// ```
+    pub fn rank(&self, val: &T) -> usize
+    where
+        C: Compare<T>,
+    {
+        let cmp = self.tree.stats.counted(&self.tree.cmp.0);
+        let mut rank = 0;
+        let mut node = &self.tree.root;
+        while let Tree::Node {
+            left, value, right, ..
+        } = node
+        {
+            if cmp.compare(&value.value, val).is_lt() {
+                rank += Size::of(left) + value.count;
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+        rank
+    }
+
+    /// Returns the value at position `index` (starting at 0) of the multiset in order,
+    /// duplicates included.
+    ///
+    /// Like [`BSTMultiset::rank`], this walks down the tree once, so it takes `O(log n)` time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BSTMultiset;
+    ///
+    /// let mut set = BSTMultiset::new();
+    /// for val in [1, 1, 2, 3] {
+    ///     set.insert(val);
+    /// }
+    /// assert_eq!(set.select(1), Some(&1));
+    /// assert_eq!(set.select(2), Some(&2));
+    /// assert_eq!(set.select(4), None);
+    /// ```
+    pub fn select(&self, mut index: usize) -> Option<&T> {
+        let mut node = &self.tree.root;
+        while let Tree::Node {
+            left, value, right, ..
+        } = node
+        {
+            let left_size = Size::of(left);
+            if index < left_size {
+                node = left;
+            } else if index < left_size + value.count {
+                return Some(&value.value);
+            } else {
+                index -= left_size + value.count;
+                node = right;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+impl<T, C> BSTMultiset<T, C> {
+    /// Helper function to check the invariants of the tree, cached sizes included.
+    pub(crate) fn check_invariants(&self) -> Result<(), crate::InvariantError>
+    where
+        C: Compare<T>,
+    {
+        self.tree.check_invariants()
+    }
+}
+
+/// An iterator over the distinct values of a [`BSTMultiset`] and their number of occurrences.
+///
+/// Created by [`BSTMultiset::iter_counts`].
+pub struct IterCounts<'a, T> {
+    inner: Iter<'a, Entry<T>, DefaultKind, Size>,
+}
+
+impl<'a, T> Iterator for IterCounts<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.value, entry.count))
+    }
+}
+
+impl<T> Clone for IterCounts<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, C> Clone for BSTMultiset<T, C>
+where
+    T: Clone,
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T, C> PartialEq for BSTMultiset<T, C>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter_counts().eq(other.iter_counts())
+    }
+}
+
+impl<T, C> fmt::Debug for BSTMultiset<T, C>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C> Extend<T> for BSTMultiset<T, C>
+where
+    C: Compare<T>,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.insert(val);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for BSTMultiset<T>
+where
+    T: Ord,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
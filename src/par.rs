@@ -0,0 +1,236 @@
+use alloc::vec::Vec;
+use core::{cmp::Ordering, mem};
+use rayon::{
+    iter::{
+        ParallelIterator,
+        plumbing::{Folder, UnindexedConsumer, UnindexedProducer, bridge_unindexed},
+    },
+    slice::ParallelSlice,
+};
+
+use crate::{BST, Tree, compare::Compare, iter::Iter, shared::Cloner, stats::Counters};
+
+/// Trees at most this deep are handled sequentially, as splitting the work would cost more
+/// than it saves.
+const SEQUENTIAL_DEPTH: usize = 8;
+
+impl<T, C> BST<T, C> {
+    /// Returns a parallel iterator over the values of the tree.
+    ///
+    /// The tree is split into subtrees handed to different threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    /// use rayon::prelude::*;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((0..1000).collect());
+    /// assert_eq!(tree.par_iter().sum::<i32>(), 499500);
+    /// ```
+    pub fn par_iter(&self) -> ParIter<'_, T>
+    where
+        T: Send + Sync,
+    {
+        ParIter { root: &self.root }
+    }
+
+    /// Same as [`BST::from_sorted`], but builds both sides of each node in parallel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values are not sorted, or if some are equal.
+    pub fn par_from_sorted(values: Vec<T>) -> Self
+    where
+        T: Send + Sync,
+        C: Compare<T> + Default + Sync,
+    {
+        let cmp = C::default();
+        assert!(
+            values
+                .par_windows(2)
+                .all(|pair| cmp.compare(&pair[0], &pair[1]) == Ordering::Less),
+            "values are not sorted"
+        );
+        let mut slots: Vec<_> = values.into_iter().map(Some).collect();
+        Self {
+            root: from_sorted(&mut slots),
+            cmp,
+            stats: Counters::default(),
+            cloner: Cloner::default(),
+        }
+    }
+
+    /// Same as [`BST::union`], but merges both sides of each split in parallel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let evens: BST<_> = BST::from_sorted((0..1000).step_by(2).collect());
+    /// let odds: BST<_> = BST::from_sorted((1..1000).step_by(2).collect());
+    /// assert!(evens.par_union(odds).iter().copied().eq(0..1000));
+    /// ```
+    pub fn par_union(mut self, other: Self) -> Self
+    where
+        T: Clone + Send + Sync,
+        C: Compare<T> + Sync,
+    {
+        let root = union(
+            mem::take(&mut self.root),
+            other.root,
+            &self.stats.counted(&self.cmp),
+        );
+        self.with_shared_root(root)
+    }
+
+    /// Same as [`BST::intersection`], but intersects both sides of each split in parallel.
+    pub fn par_intersection(mut self, other: Self) -> Self
+    where
+        T: Clone + Send + Sync,
+        C: Compare<T> + Sync,
+    {
+        let root = intersection(
+            mem::take(&mut self.root),
+            other.root,
+            &self.stats.counted(&self.cmp),
+        );
+        self.with_shared_root(root)
+    }
+}
+
+/// Builds a balanced tree out of sorted values, taking them out of their slots.
+fn from_sorted<T>(slots: &mut [Option<T>]) -> Tree<T>
+where
+    T: Send + Sync,
+{
+    let len = slots.len();
+    if len < 1 << SEQUENTIAL_DEPTH {
+        let mut values = slots
+            .iter_mut()
+            .map(|slot| slot.take().expect("slot is full"));
+        return Tree::from_sorted(len, &mut values);
+    }
+    let (left, rest) = slots.split_at_mut(len / 2);
+    let (value, right) = rest.split_first_mut().expect("right side is not empty");
+    let (left, right) = rayon::join(|| from_sorted(left), || from_sorted(right));
+    Tree::node(left, value.take().expect("slot is full"), right)
+}
+
+fn union<T, C>(tree: Tree<T>, other: Tree<T>, cmp: &C) -> Tree<T>
+where
+    T: Clone + Send + Sync,
+    C: Compare<T> + Sync,
+{
+    if tree.depth().min(other.depth()) <= SEQUENTIAL_DEPTH {
+        return tree.union(other, cmp);
+    }
+    let Some((left, value, right)) = tree.into_parts() else {
+        return other;
+    };
+    let (o_left, _, o_right) = other.split(&value, cmp);
+    let (left, right) = rayon::join(|| union(left, o_left, cmp), || union(right, o_right, cmp));
+    Tree::join(left, value, right)
+}
+
+fn intersection<T, C>(tree: Tree<T>, other: Tree<T>, cmp: &C) -> Tree<T>
+where
+    T: Clone + Send + Sync,
+    C: Compare<T> + Sync,
+{
+    if tree.depth().min(other.depth()) <= SEQUENTIAL_DEPTH {
+        return tree.intersection(other, cmp);
+    }
+    let Some((left, value, right)) = tree.into_parts() else {
+        return Tree::Empty;
+    };
+    let (o_left, found, o_right) = other.split(&value, cmp);
+    let (left, right) = rayon::join(
+        || intersection(left, o_left, cmp),
+        || intersection(right, o_right, cmp),
+    );
+    match found {
+        Some(_) => Tree::join(left, value, right),
+        None => Tree::join_pair(left, right),
+    }
+}
+
+/// A parallel iterator over the values of a [`BST`].
+///
+/// Created by [`BST::par_iter`].
+pub struct ParIter<'a, T> {
+    root: &'a Tree<T>,
+}
+
+impl<'a, T> ParallelIterator for ParIter<'a, T>
+where
+    T: Send + Sync,
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<D>(self, consumer: D) -> D::Result
+    where
+        D: UnindexedConsumer<Self::Item>,
+    {
+        let producer = Producer {
+            pieces: Vec::from([Piece::Subtree(self.root)]),
+        };
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+/// A part of the values of a tree, in order.
+enum Piece<'a, T> {
+    Subtree(&'a Tree<T>),
+    Value(&'a T),
+}
+
+/// The values still to be visited by a [`ParIter`], as consecutive pieces in order.
+struct Producer<'a, T> {
+    pieces: Vec<Piece<'a, T>>,
+}
+
+impl<'a, T> UnindexedProducer for Producer<'a, T>
+where
+    T: Send + Sync,
+{
+    type Item = &'a T;
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        // Break a lone subtree into pieces, unless it's small enough to visit in one go
+        if let [Piece::Subtree(tree)] = self.pieces[..]
+            && tree.depth() > SEQUENTIAL_DEPTH
+            && let Tree::Node {
+                left, value, right, ..
+            } = tree
+        {
+            self.pieces = Vec::from([
+                Piece::Subtree(&**left),
+                Piece::Value(value),
+                Piece::Subtree(&**right),
+            ]);
+        }
+        if self.pieces.len() < 2 {
+            return (self, None);
+        }
+        let second = self.pieces.split_off(self.pieces.len() / 2);
+        (self, Some(Self { pieces: second }))
+    }
+
+    fn fold_with<F>(self, mut folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        for piece in self.pieces {
+            folder = match piece {
+                Piece::Subtree(tree) => folder.consume_iter(Iter::new(tree)),
+                Piece::Value(value) => folder.consume(value),
+            };
+            if folder.full() {
+                break;
+            }
+        }
+        folder
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(test)]
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt};
+
+#[cfg(target_has_atomic = "ptr")]
+pub use crate::shared::ArcKind;
+pub use crate::shared::{DefaultKind, RcKind, SharedKind};
+use crate::{
+    Tree,
+    compare::{Compare, Natural},
+    iter::Iter,
+};
+
+/// An immutable AVL tree whose versions share structure.
+///
+/// [`PersistentBST::insert`] and [`PersistentBST::remove`] leave the tree untouched and return
+/// a new version, which only copies the nodes on the path to the change (cloning their values)
+/// and shares every other subtree with the old version. Cloning a version is O(1).
+///
+/// Nodes are shared through the pointer kind `P`: [`DefaultKind`] (which is [`ArcKind`] where
+/// atomic pointers are available) or [`RcKind`].
+pub struct PersistentBST<T, C = Natural, P: SharedKind = DefaultKind> {
+    root: P::Ptr<Tree<T, P>>,
+    cmp: P::Ptr<C>,
+}
+
+impl<T, C, P> Default for PersistentBST<T, C, P>
+where
+    C: Default,
+    P: SharedKind,
+{
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, P> PersistentBST<T, Natural, P>
+where
+    P: SharedKind,
+{
+    /// Creates an empty persistent tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, C, P> PersistentBST<T, C, P>
+where
+    P: SharedKind,
+{
+    /// Creates an empty persistent tree ordering its values with the given comparator.
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            root: P::new(Tree::Empty),
+            cmp: P::new(cmp),
+        }
+    }
+
+    /// Checks if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Returns a reference to the value of the root node, if the tree is not empty.
+    pub fn root_value(&self) -> Option<&T> {
+        self.root.root_value()
+    }
+
+    /// Counts the number of nodes in the tree.
+    pub fn count_nodes(&self) -> usize {
+        self.root.count_nodes()
+    }
+
+    /// Computes the depth/height of the tree, including the root node.
+    pub fn depth(&self) -> usize {
+        self.root.depth()
+    }
+
+    /// Checks if two versions are the same tree, sharing their root node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::PersistentBST;
+    ///
+    /// let v1: PersistentBST<i32> = PersistentBST::new().insert(1);
+    /// assert!(v1.ptr_eq(&v1.clone()));
+    /// assert!(v1.ptr_eq(&v1.insert(1)));
+    /// assert!(!v1.ptr_eq(&v1.insert(2)));
+    /// ```
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        P::ptr_eq(&self.root, &other.root) || (self.is_empty() && other.is_empty())
+    }
+
+    /// Returns a reference to the value in the tree equal to the given one, if any.
+    pub fn get<Q>(&self, val: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.root.find(val, &*self.cmp)?.root_value()
+    }
+
+    /// Checks if a value exists in the tree.
+    pub fn contains<Q>(&self, val: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(val).is_some()
+    }
+
+    /// Returns a new version of the tree with a value inserted.
+    ///
+    /// If the value already exists, the new version is the same tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::PersistentBST;
+    ///
+    /// let v1: PersistentBST<i32> = PersistentBST::new().insert(1).insert(2);
+    /// let v2 = v1.insert(3);
+    /// assert!(!v1.contains(&3));
+    /// assert!(v2.contains(&3));
+    /// ```
+    pub fn insert(&self, val: T) -> Self
+    where
+        T: Clone,
+        C: Compare<T>,
+    {
+        if self.contains(&val) {
+            return self.clone();
+        }
+        let mut root = self.root.shallow_copy(T::clone);
+        root.insert_missing(val, &*self.cmp, None, true, T::clone, &mut ());
+        self.with_root(root)
+    }
+
+    /// Returns a new version of the tree with a value removed.
+    ///
+    /// If the value doesn't exist, the new version is the same tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::PersistentBST;
+    ///
+    /// let v1: PersistentBST<i32> = PersistentBST::new().insert(1).insert(2);
+    /// let v2 = v1.remove(&1);
+    /// assert!(v1.contains(&1));
+    /// assert!(!v2.contains(&1));
+    /// ```
+    pub fn remove<Q>(&self, val: &Q) -> Self
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut root = self.root.shallow_copy(T::clone);
+        if !root.remove(val, &*self.cmp, T::clone, &mut ()) {
+            return self.clone();
+        }
+        self.with_root(root)
+    }
+
+    /// Returns an iterator over the values of the tree, in order.
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter::new(&self.root)
+    }
+
+    /// Helper function to make a new version of the tree around a new root.
+    fn with_root(&self, root: Tree<T, P>) -> Self {
+        Self {
+            root: P::new(root),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a [`PersistentBST`], in order.
+///
+/// Created by [`PersistentBST::iter`].
+pub type PersistentIter<'a, T, P = DefaultKind> = Iter<'a, T, P>;
+
+impl<'a, T, C, P: SharedKind> IntoIterator for &'a PersistentBST<T, C, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C, P: SharedKind> Clone for PersistentBST<T, C, P> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T, C, P> PartialEq for PersistentBST<T, C, P>
+where
+    T: PartialEq,
+    P: SharedKind,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.iter().eq(other.iter())
+    }
+}
+
+impl<T, C, P> fmt::Debug for PersistentBST<T, C, P>
+where
+    T: fmt::Debug,
+    P: SharedKind,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+impl<T, C, P: SharedKind> PersistentBST<T, C, P> {
+    /// Helper function to check that every node is ordered, balanced and caches the right
+    /// depth.
+    pub(crate) fn is_balanced(&self) -> bool
+    where
+        C: Compare<T>,
+    {
+        self.root.check_invariants(&*self.cmp, None, None).is_ok()
+    }
+
+    /// Helper function to count the nodes two versions share.
+    pub(crate) fn shared_nodes(&self, other: &Self) -> usize {
+        fn collect<T, P: SharedKind>(tree: &Tree<T, P>, out: &mut Vec<*const Tree<T, P>>) {
+            if let Tree::Node { left, right, .. } = tree {
+                out.push(tree);
+                collect(left, out);
+                collect(right, out);
+            }
+        }
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        collect(&self.root, &mut a);
+        collect(&other.root, &mut b);
+        a.iter().filter(|node| b.contains(node)).count()
+    }
+}
@@ -0,0 +1,356 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt;
+
+use crate::{BST, Tree};
+
+/// A drawing of a tree, top-down, with each parent centred over its children.
+///
+/// Created by [`BST::render`], and drawn by its [`Display`](fmt::Display) implementation.
+pub struct Render<'a, T> {
+    root: &'a Tree<T>,
+    ascii: bool,
+    max_width: Option<usize>,
+    show_depth: bool,
+    show_balance: bool,
+}
+
+impl<'a, T> Render<'a, T> {
+    pub(crate) fn new(root: &'a Tree<T>) -> Self {
+        Self {
+            root,
+            ascii: false,
+            max_width: None,
+            show_depth: false,
+            show_balance: false,
+        }
+    }
+
+    /// Draws the connectors with ASCII characters instead of box-drawing ones.
+    pub fn ascii(mut self) -> Self {
+        self.ascii = true;
+        self
+    }
+
+    /// Keeps the drawing within `width` characters.
+    ///
+    /// The deepest levels are elided until the drawing fits, and lines still too wide are
+    /// truncated.
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Shows the cached depth of each node next to its value.
+    pub fn show_depth(mut self) -> Self {
+        self.show_depth = true;
+        self
+    }
+
+    /// Shows the balance factor of each node next to its value.
+    pub fn show_balance_factor(mut self) -> Self {
+        self.show_balance = true;
+        self
+    }
+
+    /// The label standing for elided subtrees.
+    fn elided(&self) -> &'static str {
+        if self.ascii { "..." } else { "…" }
+    }
+
+    /// Lays out the first `levels` levels of a subtree.
+    fn layout(&self, tree: &Tree<T>, levels: usize) -> Option<Block>
+    where
+        T: fmt::Display,
+    {
+        let Tree::Node {
+            left,
+            value,
+            right,
+            depth,
+            ..
+        } = tree
+        else {
+            return None;
+        };
+        if levels == 0 {
+            return Some(Block::leaf(self.elided().chars().collect()));
+        }
+
+        let mut label = format!("{value}");
+        match (self.show_depth, self.show_balance) {
+            (true, true) => label += &format!(" (h={depth} bf={})", tree.balance_factor()),
+            (true, false) => label += &format!(" (h={depth})"),
+            (false, true) => label += &format!(" (bf={})", tree.balance_factor()),
+            (false, false) => (),
+        }
+        let label: Vec<char> = label.chars().collect();
+
+        let left = self.layout(left, levels - 1);
+        let right = self.layout(right, levels - 1);
+        Some(match (left, right) {
+            (None, None) => Block::leaf(label),
+            (left, right) => Block::node(label, left, right, self.ascii),
+        })
+    }
+}
+
+/// A rectangle of characters drawing a subtree.
+struct Block {
+    lines: Vec<Vec<char>>,
+    width: usize,
+    /// The column of the middle of the root's label, where the connector from its parent goes.
+    mid: usize,
+}
+
+impl Block {
+    fn leaf(label: Vec<char>) -> Self {
+        Self {
+            width: label.len(),
+            mid: label.len() / 2,
+            lines: vec![label],
+        }
+    }
+
+    /// Lays out a node over its subtrees, at least one of which isn't empty.
+    fn node(label: Vec<char>, left: Option<Self>, right: Option<Self>, ascii: bool) -> Self {
+        let len = label.len();
+        let left_width = left.as_ref().map_or(0, |left| left.width);
+        // Leave room between the children for the label to sit between their connectors
+        let gap = match (&left, &right) {
+            (Some(left), Some(right)) => (left.mid + 1 + len)
+                .saturating_sub(left_width + right.mid)
+                .max(1),
+            _ => 0,
+        };
+        let right_offset = left_width + gap;
+        let left_mid = left.as_ref().map(|left| left.mid);
+        let right_mid = right.as_ref().map(|right| right_offset + right.mid);
+
+        // Where the label starts, maybe before the children if it's wider than them
+        let start = match (left_mid, right_mid) {
+            (Some(left_mid), Some(right_mid)) => {
+                left_mid as isize + 1 + (right_mid - left_mid - 1 - len) as isize / 2
+            }
+            (Some(left_mid), None) => left_mid as isize + 1,
+            (None, Some(right_mid)) => right_mid as isize - len as isize,
+            (None, None) => unreachable!("a node block has children"),
+        };
+        let shift = start.min(0).unsigned_abs();
+        let start = (start + shift as isize) as usize;
+        let children_width = right
+            .as_ref()
+            .map_or(left_width, |right| right_offset + right.width);
+        let width = (children_width + shift).max(start + len);
+        let left_mid = left_mid.map(|mid| mid + shift);
+        let right_mid = right_mid.map(|mid| mid + shift);
+
+        let mut top = vec![' '; width];
+        top[start..start + len].copy_from_slice(&label);
+        let mut lines = Vec::new();
+        if ascii {
+            let mut connectors = vec![' '; width];
+            if let Some(left_mid) = left_mid {
+                top[left_mid + 1..start].fill('_');
+                connectors[left_mid] = '/';
+            }
+            if let Some(right_mid) = right_mid {
+                top[start + len..right_mid].fill('_');
+                connectors[right_mid] = '\\';
+            }
+            lines.push(top);
+            lines.push(connectors);
+        } else {
+            if let Some(left_mid) = left_mid {
+                top[left_mid + 1..start].fill('─');
+                top[left_mid] = '┌';
+            }
+            if let Some(right_mid) = right_mid {
+                top[start + len..right_mid].fill('─');
+                top[right_mid] = '┐';
+            }
+            lines.push(top);
+        }
+
+        let height = left
+            .as_ref()
+            .map_or(0, |left| left.lines.len())
+            .max(right.as_ref().map_or(0, |right| right.lines.len()));
+        for i in 0..height {
+            let mut line = vec![' '; width];
+            if let Some(row) = left.as_ref().and_then(|left| left.lines.get(i)) {
+                line[shift..shift + row.len()].copy_from_slice(row);
+            }
+            if let Some(row) = right.as_ref().and_then(|right| right.lines.get(i)) {
+                let offset = shift + right_offset;
+                line[offset..offset + row.len()].copy_from_slice(row);
+            }
+            lines.push(line);
+        }
+
+        Self {
+            lines,
+            width,
+            mid: start + len / 2,
+        }
+    }
+}
+
+impl<T> fmt::Display for Render<'_, T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Elide the deepest levels until the drawing fits
+        let mut levels = self.root.depth();
+        let mut block = self.layout(self.root, levels);
+        while let Some(max_width) = self.max_width
+            && block.as_ref().is_some_and(|block| block.width > max_width)
+            && levels > 1
+        {
+            levels -= 1;
+            block = self.layout(self.root, levels);
+        }
+        let Some(block) = block else {
+            return Ok(());
+        };
+
+        for (i, line) in block.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            let mut line: String = line.iter().collect();
+            line.truncate(line.trim_end().len());
+            if let Some(max_width) = self.max_width
+                && line.chars().count() > max_width
+            {
+                line = line.chars().take(max_width.saturating_sub(1)).collect();
+                line.push(if self.ascii { '~' } else { '…' });
+            }
+            f.write_str(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A debugging view of the structure of a tree.
+///
+/// Created by [`BST::tree_view`]. `{:?}` prints it on one line, nesting each subtree in
+/// parentheses, and `{:#?}` draws it sideways, right subtrees on top, with the cached depth of
+/// each node in brackets.
+pub struct TreeView<'a, T> {
+    root: &'a Tree<T>,
+}
+
+impl<'a, T> TreeView<'a, T> {
+    pub(crate) fn new(root: &'a Tree<T>) -> Self {
+        Self { root }
+    }
+}
+
+impl<T> fmt::Debug for TreeView<'_, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn fmt_inline<T: fmt::Debug>(tree: &Tree<T>, f: &mut fmt::Formatter) -> fmt::Result {
+            let Tree::Node {
+                left, value, right, ..
+            } = tree
+            else {
+                return Ok(());
+            };
+            f.write_str("(")?;
+            if !left.is_empty() {
+                fmt_inline(left, f)?;
+                f.write_str(" ")?;
+            }
+            write!(f, "{value:?}")?;
+            if !right.is_empty() {
+                f.write_str(" ")?;
+                fmt_inline(right, f)?;
+            }
+            f.write_str(")")
+        }
+
+        /// Writes the lines of a subtree, `level` levels below the root.
+        fn fmt_sideways<T: fmt::Debug>(
+            tree: &Tree<T>,
+            f: &mut fmt::Formatter,
+            level: usize,
+            first: &mut bool,
+        ) -> fmt::Result {
+            let Tree::Node {
+                left,
+                value,
+                right,
+                depth,
+                ..
+            } = tree
+            else {
+                return Ok(());
+            };
+            fmt_sideways(right, f, level + 1, first)?;
+            if !*first {
+                writeln!(f)?;
+            }
+            *first = false;
+            for _ in 0..level {
+                f.write_str("---")?;
+            }
+            write!(f, "{value:?} [{depth}]")?;
+            fmt_sideways(left, f, level + 1, first)
+        }
+
+        if self.root.is_empty() {
+            f.write_str("()")
+        } else if f.alternate() {
+            fmt_sideways(self.root, f, 0, &mut true)
+        } else {
+            fmt_inline(self.root, f)
+        }
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Returns a drawing of the tree, top-down, with each parent centred over its children.
+    ///
+    /// The drawing uses box-drawing characters by default, and can be configured before being
+    /// displayed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted(vec![2, 3, 5]);
+    /// assert_eq!(tree.render().to_string(), "┌3┐\n2 5");
+    /// assert_eq!(tree.render().ascii().to_string(), " 3\n/ \\\n2 5");
+    /// assert_eq!(
+    ///     tree.render().show_balance_factor().to_string(),
+    ///     "    ┌3 (bf=0)┐\n2 (bf=0) 5 (bf=0)"
+    /// );
+    /// ```
+    pub fn render(&self) -> Render<'_, T> {
+        Render::new(&self.root)
+    }
+
+    /// Returns a debugging view of the structure of the tree, where `{:?}` nests subtrees in
+    /// parentheses and `{:#?}` draws the tree sideways.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted(vec![1, 2, 3, 4]);
+    /// assert_eq!(format!("{tree:?}"), "{1, 2, 3, 4}");
+    /// assert_eq!(format!("{:?}", tree.tree_view()), "(((1) 2) 3 (4))");
+    /// assert_eq!(
+    ///     format!("{:#?}", tree.tree_view()),
+    ///     "---4 [1]\n3 [3]\n---2 [2]\n------1 [1]"
+    /// );
+    /// ```
+    pub fn tree_view(&self) -> TreeView<'_, T> {
+        TreeView::new(&self.root)
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::vec::Vec;
+use core::{cmp::Ordering, fmt, marker::PhantomData};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{SeqAccess, Visitor},
+    ser::SerializeSeq,
+};
+
+use crate::{BST, Tree, compare::Compare, shared::Cloner, stats::Counters};
+
+/// Serializes the tree as the sequence of its values, in order.
+impl<T, C> Serialize for BST<T, C>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.count_nodes()))?;
+        for value in self {
+            seq.serialize_element(value)?;
+        }
+        seq.end()
+    }
+}
+
+/// Deserializes a tree from a sequence of values.
+///
+/// Values sorted in strictly increasing order, as serialized, are built into a balanced tree
+/// in linear time. Any other sequence is inserted value by value.
+impl<'de, T, C> Deserialize<'de> for BST<T, C>
+where
+    T: Deserialize<'de>,
+    C: Compare<T> + Default,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(BSTVisitor(PhantomData))
+    }
+}
+
+struct BSTVisitor<T, C>(PhantomData<fn() -> BST<T, C>>);
+
+impl<'de, T, C> Visitor<'de> for BSTVisitor<T, C>
+where
+    T: Deserialize<'de>,
+    C: Compare<T> + Default,
+{
+    type Value = BST<T, C>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of values")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // Don't trust the size hint too much, it comes from the input
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        let cmp = C::default();
+        if values.is_sorted_by(|a, b| cmp.compare(a, b) == Ordering::Less) {
+            Ok(BST {
+                root: Tree::from_sorted(values.len(), &mut values.into_iter()),
+                cmp,
+                stats: Counters::default(),
+                cloner: Cloner::default(),
+            })
+        } else {
+            let mut tree = BST::with_comparator(cmp);
+            for value in values {
+                tree.insert(value);
+            }
+            Ok(tree)
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Dumps of the exact structure of a tree, to save it as it is and restore it later, and
+//! statistics about its shape.
+//!
+//! The text dump has a line per node, in preorder: its cached depth in brackets and its value,
+//! indented by two spaces per level and preceded by `L ` or `R ` for left and right children.
+//!
+//! ```text
+//! [2] 2
+//!   L [1] 1
+//!   R [1] 3
+//! ```
+//!
+//! The binary dump starts with a format version byte, then lists the subtrees in preorder:
+//! a 0 byte for an empty one, or a 1 byte, the cached depth as a varint and the value encoded
+//! by its [`Codec`].
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{fmt, fmt::Write, iter::Peekable, str::FromStr};
+
+use crate::{
+    BST, InvariantError, Tree,
+    codec::{Codec, DecodeError, read_varint, write_varint},
+    compare::Compare,
+    shared::{Cloner, Subtree},
+    stats::Counters,
+};
+
+/// The version of the binary dump format.
+pub const SHAPE_FORMAT_VERSION: u8 = 1;
+
+/// How deeply nodes can be nested in a dump, well past the depth of any balanced tree that
+/// fits in memory.
+const MAX_NESTING: usize = 128;
+
+/// The ways restoring a tree from a dump can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeDumpError {
+    /// The text dump is malformed at the given line, counting from 1.
+    Parse {
+        /// The number of the malformed line.
+        line: usize,
+    },
+    /// The binary dump is malformed.
+    Decode(DecodeError),
+    /// The dumped tree breaks an invariant.
+    Invariant(InvariantError),
+}
+
+impl fmt::Display for ShapeDumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse { line } => write!(f, "malformed dump at line {line}"),
+            Self::Decode(err) => write!(f, "malformed dump: {err}"),
+            Self::Invariant(err) => write!(f, "invalid tree: {err}"),
+        }
+    }
+}
+
+impl From<DecodeError> for ShapeDumpError {
+    fn from(err: DecodeError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl From<InvariantError> for ShapeDumpError {
+    fn from(err: InvariantError) -> Self {
+        Self::Invariant(err)
+    }
+}
+
+/// Statistics about the shape of a tree, returned by [`BST::shape_stats`].
+///
+/// Search paths are counted in nodes, from the root down to the node holding the value looked
+/// for, so the root's has length 1. Leaves are counted at the length of their search path.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapeStats {
+    /// The number of nodes.
+    pub nodes: usize,
+    /// The height of the tree, the length of its longest search path.
+    pub height: usize,
+    /// The smallest height a binary tree with as many nodes can have.
+    pub min_height: usize,
+    /// The largest height an AVL tree with as many nodes can have.
+    pub max_avl_height: usize,
+    /// The average length of the search paths of all the values.
+    pub avg_path_length: f64,
+    /// The length of the longest search path, which is the height.
+    pub max_path_length: usize,
+    /// The number of leaves at each depth.
+    pub leaf_depths: BTreeMap<usize, usize>,
+    /// The number of nodes with each balance factor.
+    pub balance_factors: BTreeMap<isize, usize>,
+}
+
+impl<T, C> BST<T, C> {
+    /// Returns statistics about the shape of the tree, to compare it to the bounds on the
+    /// height of balanced trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted((1..=4).collect());
+    /// let stats = tree.shape_stats();
+    /// assert_eq!((stats.nodes, stats.height, stats.min_height), (4, 3, 3));
+    /// // Paths of lengths 1, 2, 2 and 3
+    /// assert_eq!(stats.avg_path_length, 2.0);
+    /// assert_eq!(stats.leaf_depths.into_iter().collect::<Vec<_>>(), [(2, 1), (3, 1)]);
+    /// assert_eq!(stats.balance_factors.into_iter().collect::<Vec<_>>(), [(0, 2), (1, 2)]);
+    /// ```
+    pub fn shape_stats(&self) -> ShapeStats {
+        fn walk<T>(tree: &Tree<T>, length: usize, stats: &mut ShapeStats, total: &mut usize) {
+            let Tree::Node { left, right, .. } = tree else {
+                return;
+            };
+            stats.nodes += 1;
+            *total += length;
+            stats.max_path_length = stats.max_path_length.max(length);
+            *stats
+                .balance_factors
+                .entry(tree.balance_factor())
+                .or_default() += 1;
+            if left.is_empty() && right.is_empty() {
+                *stats.leaf_depths.entry(length).or_default() += 1;
+            }
+            walk(left, length + 1, stats, total);
+            walk(right, length + 1, stats, total);
+        }
+
+        let mut stats = ShapeStats::default();
+        let mut total = 0;
+        walk(&self.root, 1, &mut stats, &mut total);
+        stats.height = self.root.depth();
+        // A tree of height h holds at most 2^h - 1 nodes
+        stats.min_height = (usize::BITS - stats.nodes.leading_zeros()) as usize;
+        // The smallest AVL tree of height h is a root over the smallest ones of heights h - 1
+        // and h - 2
+        let (mut smaller, mut smallest) = (1, 0);
+        while smaller <= stats.nodes {
+            (smaller, smallest) = (smaller + smallest + 1, smaller);
+            stats.max_avl_height += 1;
+        }
+        if stats.nodes > 0 {
+            stats.avg_path_length = total as f64 / stats.nodes as f64;
+        }
+        stats
+    }
+
+    /// Dumps the exact structure of the tree as text, which [`BST::from_shape_dump`] restores.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted(vec![1, 2, 3, 4]);
+    /// assert_eq!(tree.to_shape_dump(), "[3] 3\n  L [2] 2\n    L [1] 1\n  R [1] 4\n");
+    /// ```
+    pub fn to_shape_dump(&self) -> String
+    where
+        T: fmt::Display,
+    {
+        fn dump<T: fmt::Display>(tree: &Tree<T>, prefix: &str, level: usize, out: &mut String) {
+            if let Tree::Node {
+                left,
+                value,
+                right,
+                depth,
+                ..
+            } = tree
+            {
+                let indent = level * 2;
+                writeln!(out, "{:indent$}{prefix}[{depth}] {value}", "")
+                    .expect("writing to a string can't fail");
+                dump(left, "L ", level + 1, out);
+                dump(right, "R ", level + 1, out);
+            }
+        }
+
+        let mut out = String::new();
+        dump(&self.root, "", 0, &mut out);
+        out
+    }
+
+    /// Restores a tree from a text dump made by [`BST::to_shape_dump`], with the exact same
+    /// structure.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ShapeDumpError`] if the dump is malformed, or if the tree it describes
+    /// breaks an invariant, in which case it is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, InvariantError, shape::ShapeDumpError};
+    ///
+    /// let tree: BST<i32> = BST::from_shape_dump("[2] 1\n  R [1] 2\n").unwrap();
+    /// assert_eq!(tree.root().right().and_then(|t| t.root_value()), Some(&2));
+    ///
+    /// let wrong_depth = BST::<i32>::from_shape_dump("[3] 1\n  R [1] 2\n");
+    /// assert_eq!(wrong_depth, Err(ShapeDumpError::Invariant(InvariantError::WrongDepth)));
+    /// ```
+    pub fn from_shape_dump(dump: &str) -> Result<Self, ShapeDumpError>
+    where
+        T: FromStr,
+        C: Compare<T> + Default,
+    {
+        let mut lines = dump
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+        let root = match lines.next() {
+            None => Tree::Empty,
+            Some((number, line)) => parse_node(number, line, 0, &mut lines)?,
+        };
+        if let Some((line, _)) = lines.next() {
+            return Err(ShapeDumpError::Parse { line });
+        }
+        Self::checked(root)
+    }
+
+    /// Dumps the exact structure of the tree in binary, which
+    /// [`BST::from_shape_dump_bytes`] restores.
+    pub fn to_shape_dump_bytes(&self) -> Vec<u8>
+    where
+        T: Codec,
+    {
+        fn dump<T: Codec>(tree: &Tree<T>, out: &mut Vec<u8>) {
+            match tree {
+                Tree::Empty => out.push(0),
+                Tree::Node {
+                    left,
+                    value,
+                    right,
+                    depth,
+                    ..
+                } => {
+                    out.push(1);
+                    let Ok(()) = write_varint(*depth as u128, out);
+                    let Ok(()) = value.encode(None, out);
+                    dump(left, out);
+                    dump(right, out);
+                }
+            }
+        }
+
+        let mut out = Vec::from([SHAPE_FORMAT_VERSION]);
+        dump(&self.root, &mut out);
+        out
+    }
+
+    /// Restores a tree from a binary dump made by [`BST::to_shape_dump_bytes`], with the
+    /// exact same structure.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ShapeDumpError`] if the dump is malformed, or if the tree it describes
+    /// breaks an invariant, in which case it is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// for i in [5, 2, 8, 1, 9, 3] {
+    ///     tree.insert(i);
+    /// }
+    /// let bytes = tree.to_shape_dump_bytes();
+    /// let restored = BST::<i32>::from_shape_dump_bytes(&bytes).unwrap();
+    /// assert_eq!(restored.to_shape_dump(), tree.to_shape_dump());
+    /// ```
+    pub fn from_shape_dump_bytes(bytes: &[u8]) -> Result<Self, ShapeDumpError>
+    where
+        T: Codec,
+        C: Compare<T> + Default,
+    {
+        fn restore<T: Codec>(input: &mut &[u8], level: usize) -> Result<Tree<T>, ShapeDumpError> {
+            let (&tag, rest) = input.split_first().ok_or(DecodeError::Truncated)?;
+            *input = rest;
+            match tag {
+                0 => Ok(Tree::Empty),
+                1 if level >= MAX_NESTING => Err(InvariantError::Unbalanced.into()),
+                1 => {
+                    let depth =
+                        usize::try_from(read_varint(input)?).map_err(|_| DecodeError::Invalid)?;
+                    let value = T::decode(None, input)?;
+                    let left = restore(input, level + 1)?;
+                    let right = restore(input, level + 1)?;
+                    Ok(Tree::Node {
+                        left: Subtree::new(left),
+                        value,
+                        right: Subtree::new(right),
+                        depth,
+                        aug: (),
+                    })
+                }
+                _ => Err(DecodeError::Invalid.into()),
+            }
+        }
+
+        let mut input = bytes;
+        let (&version, rest) = input.split_first().ok_or(DecodeError::Truncated)?;
+        input = rest;
+        if version != SHAPE_FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version).into());
+        }
+        let root = restore(&mut input, 0)?;
+        if !input.is_empty() {
+            return Err(DecodeError::TrailingData.into());
+        }
+        Self::checked(root)
+    }
+
+    /// Helper function to wrap a restored tree, if it keeps every invariant.
+    fn checked(root: Tree<T>) -> Result<Self, ShapeDumpError>
+    where
+        C: Compare<T> + Default,
+    {
+        let tree = Self {
+            root,
+            cmp: C::default(),
+            stats: Counters::default(),
+            cloner: Cloner::default(),
+        };
+        tree.check_invariants()?;
+        Ok(tree)
+    }
+}
+
+/// Parses the node on line `number` of a text dump, nested at `level`, and its subtrees on the
+/// following lines.
+fn parse_node<'a, T, I>(
+    number: usize,
+    line: &str,
+    level: usize,
+    lines: &mut Peekable<I>,
+) -> Result<Tree<T>, ShapeDumpError>
+where
+    T: FromStr,
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let malformed = ShapeDumpError::Parse { line: number };
+    let (depth, value) = line
+        .strip_prefix('[')
+        .and_then(|line| line.split_once("] "))
+        .ok_or(malformed)?;
+    let depth = depth.parse().map_err(|_| malformed)?;
+    let value = value.parse().map_err(|_| malformed)?;
+
+    let mut left = Tree::Empty;
+    let mut right = Tree::Empty;
+    let child_indent = (level + 1) * 2;
+    while let Some(&(number, line)) = lines.peek() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent <= level * 2 {
+            break;
+        }
+        lines.next();
+        let malformed = ShapeDumpError::Parse { line: number };
+        if indent != child_indent {
+            return Err(malformed);
+        }
+        if level + 1 >= MAX_NESTING {
+            return Err(InvariantError::Unbalanced.into());
+        }
+        let line = &line[indent..];
+        // The left child comes first, and each child at most once
+        if let Some(line) = line.strip_prefix("L ")
+            && left.is_empty()
+            && right.is_empty()
+        {
+            left = parse_node(number, line, level + 1, lines)?;
+        } else if let Some(line) = line.strip_prefix("R ")
+            && right.is_empty()
+        {
+            right = parse_node(number, line, level + 1, lines)?;
+        } else {
+            return Err(malformed);
+        }
+    }
+
+    Ok(Tree::Node {
+        left: Subtree::new(left),
+        value,
+        right: Subtree::new(right),
+        depth,
+        aug: (),
+    })
+}
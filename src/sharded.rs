@@ -0,0 +1,460 @@
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    ops::{Bound, RangeBounds},
+};
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard};
+
+use crate::{
+    BST,
+    compare::{Compare, KeyOrder, Natural},
+};
+
+/// A key of the map, with its value.
+#[derive(Clone)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K, V> Borrow<K> for Entry<K, V> {
+    fn borrow(&self) -> &K {
+        &self.key
+    }
+}
+
+/// The keys of a shard, and how many there are.
+struct Shard<K, V, C> {
+    tree: BST<Entry<K, V>, KeyOrder<C, K>>,
+    len: usize,
+}
+
+impl<K, V, C> Shard<K, V, C> {
+    fn new(cmp: C) -> Self {
+        Self {
+            tree: BST::with_comparator(KeyOrder::new(cmp)),
+            len: 0,
+        }
+    }
+
+    /// Splits the shard in two halves sharing its nodes, returning the first key of the upper
+    /// half with them. The shard itself is left as it was.
+    fn halves(&self) -> (Self, K, Self)
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K> + Clone,
+    {
+        let half = self.len / 2;
+        let middle = self
+            .tree
+            .iter()
+            .nth(half)
+            .expect("shard holds `len` keys")
+            .clone();
+        let upper_start = middle.key.clone();
+        let (lower, upper) = self.tree.split_shared(middle);
+        (
+            Self {
+                tree: lower,
+                len: half,
+            },
+            upper_start,
+            Self {
+                tree: upper,
+                len: self.len - half,
+            },
+        )
+    }
+}
+
+/// The shards in order, with the smallest key each can hold (the first one holds any key
+/// before the second one's).
+type Shards<K, V, C> = Vec<(Option<K>, Mutex<Shard<K, V, C>>)>;
+
+/// An ordered map shared between threads, split into shards of consecutive keys so that
+/// writers to different shards don't wait for each other.
+///
+/// Each shard is a balanced [`BST`] behind its own lock. A shard growing past the maximum
+/// size given to [`ShardedBSTMap::with_shard_size`] is split in two halves, and one shrinking
+/// below a quarter of it is merged with its smaller neighbour, the pair being split in halves
+/// again should it be too big. Splitting and merging lock the whole map
+/// for a moment, as they split and join the trees of the shards rather than rebuilding them.
+///
+/// A thread panicking while holding a lock doesn't make the map unusable: the shards are
+/// never left half changed, so the next thread carries on with them.
+///
+/// # Examples
+///
+/// ```
+/// use bst::ShardedBSTMap;
+/// use std::{sync::Arc, thread};
+///
+/// let map = Arc::new(ShardedBSTMap::with_shard_size(16));
+/// let handles: Vec<_> = (0..4)
+///     .map(|t| {
+///         let map = Arc::clone(&map);
+///         thread::spawn(move || {
+///             for i in 0..25 {
+///                 map.insert(i * 4 + t, t);
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(map.len(), 100);
+/// assert!(map.shard_count() > 1);
+/// assert_eq!(map.range(10..14), [(10, 2), (11, 3), (12, 0), (13, 1)]);
+/// ```
+pub struct ShardedBSTMap<K, V, C = Natural> {
+    shards: RwLock<Shards<K, V, C>>,
+    max_shard_len: usize,
+    cmp: C,
+}
+
+impl<K, V, C> Default for ShardedBSTMap<K, V, C>
+where
+    C: Default + Clone,
+{
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<K, V> ShardedBSTMap<K, V> {
+    /// Creates an empty map, whose shards hold up to 1024 keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map, whose shards hold up to `max_len` keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_len` is less than 4.
+    pub fn with_shard_size(max_len: usize) -> Self {
+        Self::with_comparator_and_shard_size(Natural, max_len)
+    }
+}
+
+impl<K, V, C> ShardedBSTMap<K, V, C> {
+    /// Creates an empty map ordering its keys with the given comparator, whose shards hold up
+    /// to 1024 keys.
+    pub fn with_comparator(cmp: C) -> Self
+    where
+        C: Clone,
+    {
+        Self::with_comparator_and_shard_size(cmp, 1024)
+    }
+
+    /// Creates an empty map ordering its keys with the given comparator, whose shards hold up
+    /// to `max_len` keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_len` is less than 4.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::ShardedBSTMap;
+    ///
+    /// let map = ShardedBSTMap::with_comparator_and_shard_size(|a: &i32, b: &i32| b.cmp(a), 4);
+    /// for key in 0..10 {
+    ///     map.insert(key, ());
+    /// }
+    /// assert!(map.shard_count() > 1);
+    /// assert_eq!(map.range(..7), [(9, ()), (8, ())]);
+    /// assert_eq!(map.range(2..0), [(2, ()), (1, ())]);
+    /// ```
+    pub fn with_comparator_and_shard_size(cmp: C, max_len: usize) -> Self
+    where
+        C: Clone,
+    {
+        assert!(max_len >= 4, "shards must hold at least 4 keys");
+        Self {
+            shards: RwLock::new(Vec::from([(None, Mutex::new(Shard::new(cmp.clone())))])),
+            max_shard_len: max_len,
+            cmp,
+        }
+    }
+
+    /// Returns the number of shards the keys are currently split into.
+    pub fn shard_count(&self) -> usize {
+        self.read_shards().len()
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> usize {
+        let shards = self.read_shards();
+        shards.iter().map(|(_, shard)| lock(shard).len).sum()
+    }
+
+    /// Checks if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a copy of the value of a key, if it is in the map.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+        C: Compare<K>,
+    {
+        let shards = self.read_shards();
+        let shard = lock(&shards[self.shard_index(&shards, key)].1);
+        shard.tree.get(key).map(|entry| entry.value.clone())
+    }
+
+    /// Checks if a key is in the map.
+    pub fn contains_key(&self, key: &K) -> bool
+    where
+        C: Compare<K>,
+    {
+        let shards = self.read_shards();
+        let shard = lock(&shards[self.shard_index(&shards, key)].1);
+        shard.tree.contains(key)
+    }
+
+    /// Sets the value of a key, returning its previous value if it was already in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::ShardedBSTMap;
+    ///
+    /// let map = ShardedBSTMap::new();
+    /// assert_eq!(map.insert(1, "a"), None);
+    /// assert_eq!(map.insert(1, "b"), Some("a"));
+    /// assert_eq!(map.get(&1), Some("b"));
+    /// ```
+    pub fn insert(&self, key: K, value: V) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K> + Clone,
+    {
+        let (old, len) = {
+            let shards = self.read_shards();
+            let mut shard = lock(&shards[self.shard_index(&shards, &key)].1);
+            let mut value = Some(value);
+            let mut old = None;
+            shard.tree.modify(&key, |entry| {
+                old = value
+                    .take()
+                    .map(|value| core::mem::replace(&mut entry.value, value));
+            });
+            if let Some(value) = value {
+                shard.tree.insert(Entry {
+                    key: key.clone(),
+                    value,
+                });
+                shard.len += 1;
+            }
+            (old, shard.len)
+        };
+        if len > self.max_shard_len {
+            self.split_shard(&key);
+        }
+        old
+    }
+
+    /// Removes a key from the map, returning its value if it was in the map.
+    pub fn remove(&self, key: &K) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K> + Clone,
+    {
+        let (old, len) = {
+            let shards = self.read_shards();
+            let mut shard = lock(&shards[self.shard_index(&shards, key)].1);
+            let old = shard.tree.get(key).map(|entry| entry.value.clone());
+            if old.is_some() {
+                shard.tree.remove(key);
+                shard.len -= 1;
+            }
+            (old, shard.len)
+        };
+        if old.is_some() && len < self.min_shard_len() {
+            self.merge_shard(key);
+        }
+        old
+    }
+
+    /// Returns copies of the keys and values within a range, in order.
+    ///
+    /// The shards overlapping the range are all locked while they are read, so the result is
+    /// a consistent view of the map.
+    pub fn range<R>(&self, range: R) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K>,
+        R: RangeBounds<K>,
+    {
+        let shards = self.read_shards();
+        let first = match range.start_bound() {
+            Bound::Included(start) | Bound::Excluded(start) => self.shard_index(&shards, start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) | Bound::Excluded(end) => self.shard_index(&shards, end) + 1,
+            Bound::Unbounded => shards.len(),
+        };
+        if first >= end {
+            return Vec::new();
+        }
+
+        // Lock every shard first, always in order so as not to deadlock with another range
+        let locked: Vec<_> = shards[first..end]
+            .iter()
+            .map(|(_, shard)| lock(shard))
+            .collect();
+        // The shards hold consecutive keys, so their ranges just follow each other
+        locked
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .tree
+                    .range::<K, _>((range.start_bound(), range.end_bound()))
+                    .map(|entry| (entry.key.clone(), entry.value.clone()))
+            })
+            .collect()
+    }
+
+    fn min_shard_len(&self) -> usize {
+        self.max_shard_len / 4
+    }
+
+    fn read_shards(&self) -> RwLockReadGuard<'_, Shards<K, V, C>> {
+        self.shards.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Finds the index of the shard that holds `key`.
+    fn shard_index<S>(&self, shards: &[(Option<K>, S)], key: &K) -> usize
+    where
+        C: Compare<K>,
+    {
+        shards.partition_point(|(start, _)| {
+            start
+                .as_ref()
+                .is_none_or(|start| self.cmp.compare(start, key) != Ordering::Greater)
+        }) - 1
+    }
+
+    /// Splits the shard holding `key` in two halves, if it is still too big.
+    fn split_shard(&self, key: &K)
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K> + Clone,
+    {
+        let mut shards = self.shards.write().unwrap_or_else(PoisonError::into_inner);
+        let index = self.shard_index(&shards, key);
+        let shard = shards[index]
+            .1
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if shard.len <= self.max_shard_len {
+            return;
+        }
+
+        // The halves share the nodes of the shard, which is left as it was if cloning an entry
+        // panics
+        let (lower, upper_start, upper) = shard.halves();
+        *shard = lower;
+        shards.insert(index + 1, (Some(upper_start), Mutex::new(upper)));
+    }
+
+    /// Merges the shard holding `key` with its smaller neighbour, if it is still too small.
+    fn merge_shard(&self, key: &K)
+    where
+        K: Clone,
+        V: Clone,
+        C: Compare<K> + Clone,
+    {
+        let mut shards = self.shards.write().unwrap_or_else(PoisonError::into_inner);
+        if shards.len() == 1 {
+            return;
+        }
+        let index = self.shard_index(&shards, key);
+        let last = shards.len() - 1;
+        let mut len = |index: usize| {
+            shards[index]
+                .1
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len
+        };
+        if len(index) >= self.min_shard_len() {
+            return;
+        }
+        let later = if index == 0 {
+            1
+        } else if index == last || len(index - 1) <= len(index + 1) {
+            index
+        } else {
+            index + 1
+        };
+
+        // Merge the later shard of the pair into the earlier one, which keeps its start. The
+        // merged shard shares the nodes of both, which are left as they were if cloning an
+        // entry panics
+        let [(_, into), (_, merged)] = &mut shards[later - 1..=later] else {
+            unreachable!("the pair holds two shards");
+        };
+        let into = into.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let merged = merged.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let joined = Shard {
+            tree: into.tree.join_shared(&merged.tree),
+            len: into.len + merged.len,
+        };
+        // A small shard merged with a full one may need splitting again
+        if joined.len > self.max_shard_len {
+            let (lower, upper_start, upper) = joined.halves();
+            *into = lower;
+            shards[later] = (Some(upper_start), Mutex::new(upper));
+        } else {
+            *into = joined;
+            shards.remove(later);
+        }
+    }
+}
+
+#[cfg(test)]
+impl<K, V, C> ShardedBSTMap<K, V, C> {
+    /// Helper function to get the number of keys in each shard, in order.
+    pub(crate) fn shard_lens(&self) -> Vec<usize> {
+        let shards = self.read_shards();
+        shards.iter().map(|(_, shard)| lock(shard).len).collect()
+    }
+}
+
+/// Locks a shard.
+fn lock<K, V, C>(shard: &Mutex<Shard<K, V, C>>) -> MutexGuard<'_, Shard<K, V, C>> {
+    shard.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K, V, C> fmt::Debug for ShardedBSTMap<K, V, C>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shards = self.read_shards();
+        let locked: Vec<_> = shards.iter().map(|(_, shard)| lock(shard)).collect();
+        f.debug_map()
+            .entries(
+                locked
+                    .iter()
+                    .flat_map(|shard| shard.tree.iter())
+                    .map(|entry| (&entry.key, &entry.value)),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,250 @@
+//! How trees share their nodes with their clones, and copy them before changing them.
+
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+#[cfg(not(target_has_atomic = "ptr"))]
+use core::cell::Cell;
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::{AtomicPtr, Ordering::Relaxed};
+use core::{fmt, marker::PhantomData, mem, ops::Deref};
+
+use crate::tree::{Augment, Observer, Tree};
+
+/// A function cloning values, used to copy the nodes a tree shares before changing them.
+pub(crate) type CloneFn<T> = fn(&T) -> T;
+
+/// A kind of reference-counted pointer the nodes of a tree are shared through.
+pub trait SharedKind {
+    /// The pointer type to a `N`.
+    type Ptr<N>: Deref<Target = N> + Clone;
+
+    /// Allocates a value behind a new pointer.
+    fn new<N>(value: N) -> Self::Ptr<N>;
+
+    /// Checks if two pointers point to the same allocation.
+    fn ptr_eq<N>(a: &Self::Ptr<N>, b: &Self::Ptr<N>) -> bool;
+
+    /// Returns a mutable reference to the value, if no other pointer shares it.
+    fn get_mut<N>(ptr: &mut Self::Ptr<N>) -> Option<&mut N>;
+
+    /// Returns the value, if no other pointer shares it, or the pointer back otherwise.
+    fn try_unwrap<N>(ptr: Self::Ptr<N>) -> Result<N, Self::Ptr<N>>;
+}
+
+/// Shares nodes through [`Rc`], for trees that stay on one thread.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RcKind;
+
+impl SharedKind for RcKind {
+    type Ptr<N> = Rc<N>;
+
+    fn new<N>(value: N) -> Rc<N> {
+        Rc::new(value)
+    }
+
+    fn ptr_eq<N>(a: &Rc<N>, b: &Rc<N>) -> bool {
+        Rc::ptr_eq(a, b)
+    }
+
+    fn get_mut<N>(ptr: &mut Rc<N>) -> Option<&mut N> {
+        Rc::get_mut(ptr)
+    }
+
+    fn try_unwrap<N>(ptr: Rc<N>) -> Result<N, Rc<N>> {
+        Rc::try_unwrap(ptr)
+    }
+}
+
+/// Shares nodes through [`Arc`], for trees sent or shared across threads.
+///
+/// Only available on targets with atomic pointers.
+#[cfg(target_has_atomic = "ptr")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArcKind;
+
+#[cfg(target_has_atomic = "ptr")]
+impl SharedKind for ArcKind {
+    type Ptr<N> = Arc<N>;
+
+    fn new<N>(value: N) -> Arc<N> {
+        Arc::new(value)
+    }
+
+    fn ptr_eq<N>(a: &Arc<N>, b: &Arc<N>) -> bool {
+        Arc::ptr_eq(a, b)
+    }
+
+    fn get_mut<N>(ptr: &mut Arc<N>) -> Option<&mut N> {
+        Arc::get_mut(ptr)
+    }
+
+    fn try_unwrap<N>(ptr: Arc<N>) -> Result<N, Arc<N>> {
+        Arc::try_unwrap(ptr)
+    }
+}
+
+/// The kind of pointer trees share their nodes through unless told otherwise: [`ArcKind`], or
+/// [`RcKind`] on targets without atomic pointers.
+#[cfg(target_has_atomic = "ptr")]
+pub type DefaultKind = ArcKind;
+
+/// The kind of pointer trees share their nodes through unless told otherwise: [`ArcKind`], or
+/// [`RcKind`] on targets without atomic pointers.
+#[cfg(not(target_has_atomic = "ptr"))]
+pub type DefaultKind = RcKind;
+
+/// A subtree under a node, which the clones of a [`BST`](crate::BST) share.
+///
+/// It dereferences to the subtree. Only the tree itself shares it, so that changing a tree
+/// never meets a node shared behind its back.
+pub struct Subtree<T, K: SharedKind = DefaultKind, A = ()>(K::Ptr<Tree<T, K, A>>);
+
+impl<T, K: SharedKind, A> Subtree<T, K, A> {
+    pub(crate) fn new(tree: Tree<T, K, A>) -> Self {
+        Self(K::new(tree))
+    }
+
+    /// Checks if two subtrees are the same nodes, shared between clones of a tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, Subtree, Tree};
+    ///
+    /// let mut tree = BST::new();
+    /// for i in 0..3 {
+    ///     tree.insert(i);
+    /// }
+    /// let copy = tree.clone();
+    /// let (Tree::Node { left: a, .. }, Tree::Node { left: b, .. }) = (tree.root(), copy.root())
+    /// else {
+    ///     unreachable!("trees aren't empty");
+    /// };
+    /// assert!(Subtree::ptr_eq(a, b));
+    /// ```
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        K::ptr_eq(&a.0, &b.0)
+    }
+
+    /// Helper function to share the subtree with another tree.
+    pub(crate) fn share(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    /// Helper function to check if another tree shares the subtree.
+    pub(crate) fn is_shared(&mut self) -> bool {
+        K::get_mut(&mut self.0).is_none()
+    }
+
+    /// Same as [`Rc::make_mut`], copying the root node with `clone` if another tree shares
+    /// it, and telling `obs` when it does.
+    pub(crate) fn make_mut<O>(&mut self, clone: CloneFn<T>, obs: &mut O) -> &mut Tree<T, K, A>
+    where
+        A: Augment<T>,
+        O: Observer<T, K, A>,
+    {
+        if self.is_shared() {
+            if !self.is_empty() {
+                obs.allocated();
+            }
+            self.0 = K::new(self.0.shallow_copy(clone));
+        }
+        K::get_mut(&mut self.0).expect("subtree is no longer shared")
+    }
+
+    /// Same as [`Rc::unwrap_or_clone`], copying the root node with `clone` if another tree
+    /// shares it, and telling `obs` when it does.
+    pub(crate) fn into_tree<O>(self, clone: CloneFn<T>, obs: &mut O) -> Tree<T, K, A>
+    where
+        A: Augment<T>,
+        O: Observer<T, K, A>,
+    {
+        K::try_unwrap(self.0).unwrap_or_else(|tree| {
+            if !tree.is_empty() {
+                obs.allocated();
+            }
+            tree.shallow_copy(clone)
+        })
+    }
+}
+
+impl<T, K: SharedKind, A> Deref for Subtree<T, K, A> {
+    type Target = Tree<T, K, A>;
+
+    fn deref(&self) -> &Tree<T, K, A> {
+        &self.0
+    }
+}
+
+impl<T, K, A> fmt::Debug for Subtree<T, K, A>
+where
+    K: SharedKind,
+    Tree<T, K, A>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Where a tree records how to clone its values, once it shares nodes with another tree.
+///
+/// Nodes are only ever shared by operations requiring `T: Clone`, which record it first, so
+/// that the operations changing the tree can copy the shared nodes without requiring it too.
+///
+/// The invariant this relies on is that a [`BST`](crate::BST) holding a node whose pointer is
+/// shared has its function recorded: its `Clone` impl records it on both trees, trees built
+/// from shared nodes go through `BST::with_shared_root`, and the others sharing their nodes
+/// (with a transaction snapshot, or a recording) record it first. A tree breaking it panics
+/// when it next changes a shared node, rather than copying it unsoundly.
+pub(crate) struct Cloner<T> {
+    /// The [`CloneFn`] recorded, or null.
+    #[cfg(target_has_atomic = "ptr")]
+    clone: AtomicPtr<()>,
+    /// The [`CloneFn`] recorded, or null.
+    #[cfg(not(target_has_atomic = "ptr"))]
+    clone: Cell<*mut ()>,
+    marker: PhantomData<CloneFn<T>>,
+}
+
+impl<T> Default for Cloner<T> {
+    fn default() -> Self {
+        Self {
+            clone: Default::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Cloner<T> {
+    /// Records how to clone the values, before sharing nodes.
+    pub(crate) fn record(&self)
+    where
+        T: Clone,
+    {
+        let clone = <T as Clone>::clone as CloneFn<T> as *mut ();
+        #[cfg(target_has_atomic = "ptr")]
+        self.clone.store(clone, Relaxed);
+        #[cfg(not(target_has_atomic = "ptr"))]
+        self.clone.set(clone);
+    }
+
+    /// Returns the function recorded, or one panicking if the tree never shared its nodes.
+    pub(crate) fn get(&self) -> CloneFn<T> {
+        #[cfg(target_has_atomic = "ptr")]
+        let clone = self.clone.load(Relaxed);
+        #[cfg(not(target_has_atomic = "ptr"))]
+        let clone = self.clone.get();
+        if clone.is_null() {
+            return unshared;
+        }
+        // SAFETY: `record` only ever stores a `CloneFn<T>`
+        unsafe { mem::transmute::<*mut (), CloneFn<T>>(clone) }
+    }
+}
+
+/// Stands in for the clone function of a tree which never shared its nodes, and thus never
+/// copies them.
+fn unshared<T>(_: &T) -> T {
+    unreachable!("nodes are only shared once their clone function is recorded")
+}
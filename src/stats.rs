@@ -0,0 +1,191 @@
+#[cfg(all(feature = "stats", not(target_has_atomic = "ptr")))]
+use core::cell::Cell;
+use core::cmp::Ordering;
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+#[cfg(feature = "stats")]
+use crate::{BST, Tree, tree::Rotation};
+use crate::{compare::Compare, shared::SharedKind, tree::Observer};
+
+/// The counters of the work done by a tree, which take no room and do nothing unless the
+/// `stats` feature is enabled.
+#[derive(Default)]
+pub(crate) struct Counters {
+    #[cfg(feature = "stats")]
+    comparisons: Counter,
+    #[cfg(feature = "stats")]
+    rotations: Counter,
+    #[cfg(feature = "stats")]
+    allocations: Counter,
+    #[cfg(feature = "stats")]
+    frees: Counter,
+}
+
+/// A counter bumped by operations which may only read the tree, from any thread holding it.
+///
+/// Only targets with atomics share trees between threads, so the others make do with a
+/// [`Cell`](core::cell::Cell).
+#[cfg(feature = "stats")]
+#[derive(Default)]
+struct Counter(
+    #[cfg(target_has_atomic = "ptr")] AtomicUsize,
+    #[cfg(not(target_has_atomic = "ptr"))] Cell<usize>,
+);
+
+#[cfg(feature = "stats")]
+impl Counter {
+    fn new(count: u64) -> Self {
+        let count = count as usize;
+        #[cfg(target_has_atomic = "ptr")]
+        return Self(AtomicUsize::new(count));
+        #[cfg(not(target_has_atomic = "ptr"))]
+        return Self(Cell::new(count));
+    }
+
+    #[inline]
+    fn add(&self, count: usize) {
+        #[cfg(target_has_atomic = "ptr")]
+        self.0.fetch_add(count, Relaxed);
+        #[cfg(not(target_has_atomic = "ptr"))]
+        self.0.set(self.0.get().wrapping_add(count));
+    }
+
+    fn get(&self) -> u64 {
+        #[cfg(target_has_atomic = "ptr")]
+        return self.0.load(Relaxed) as u64;
+        #[cfg(not(target_has_atomic = "ptr"))]
+        return self.0.get() as u64;
+    }
+}
+
+impl Counters {
+    /// Wraps `cmp` to count the comparisons it makes.
+    #[inline]
+    pub(crate) fn counted<'a, C>(&'a self, cmp: &'a C) -> Counted<'a, C> {
+        Counted {
+            cmp,
+            #[cfg(feature = "stats")]
+            counters: self,
+        }
+    }
+}
+
+/// A clone of a tree starts from the counts of the original.
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        #[cfg(feature = "stats")]
+        {
+            let stats = self.snapshot();
+            Self {
+                comparisons: Counter::new(stats.comparisons),
+                rotations: Counter::new(stats.rotations),
+                allocations: Counter::new(stats.allocations),
+                frees: Counter::new(stats.frees),
+            }
+        }
+        #[cfg(not(feature = "stats"))]
+        Self {}
+    }
+}
+
+/// Counts the rotations, allocations and frees of the operations it observes.
+impl<T, K: SharedKind, A> Observer<T, K, A> for &Counters {
+    #[cfg(feature = "stats")]
+    fn before_rotation(&mut self, rotation: Rotation, _tree: &Tree<T, K, A>) {
+        let count = match rotation {
+            Rotation::Left | Rotation::Right => 1,
+            Rotation::LeftRight | Rotation::RightLeft => 2,
+        };
+        self.rotations.add(count);
+    }
+
+    #[cfg(feature = "stats")]
+    fn allocated(&mut self) {
+        self.allocations.add(1);
+    }
+
+    #[cfg(feature = "stats")]
+    fn freed(&mut self) {
+        self.frees.add(1);
+    }
+}
+
+/// A comparator counting the comparisons it makes.
+pub(crate) struct Counted<'a, C> {
+    cmp: &'a C,
+    #[cfg(feature = "stats")]
+    counters: &'a Counters,
+}
+
+impl<T, C> Compare<T> for Counted<'_, C>
+where
+    T: ?Sized,
+    C: Compare<T>,
+{
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        #[cfg(feature = "stats")]
+        self.counters.comparisons.add(1);
+        self.cmp.compare(a, b)
+    }
+}
+
+/// A snapshot of the counters a tree keeps of its work.
+///
+/// The counters add up from the creation of the tree, or from the last call to
+/// [`BST::reset_stats`]. Set operations only count their comparisons.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The comparisons made by searches and changes.
+    pub comparisons: u64,
+    /// The rotations made rebalancing the tree, a double rotation counting as two.
+    pub rotations: u64,
+    /// The nodes allocated, for new values and to copy nodes shared with clones of the tree.
+    pub allocations: u64,
+    /// The nodes freed as values were removed.
+    pub frees: u64,
+}
+
+#[cfg(feature = "stats")]
+impl Counters {
+    fn snapshot(&self) -> TreeStats {
+        TreeStats {
+            comparisons: self.comparisons.get(),
+            rotations: self.rotations.get(),
+            allocations: self.allocations.get(),
+            frees: self.frees.get(),
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl<T, C, A> BST<T, C, A> {
+    /// Returns the counters of the work done by the tree so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// for i in [1, 2, 3] {
+    ///     tree.insert(i);
+    /// }
+    /// let stats = tree.stats();
+    /// assert_eq!((stats.comparisons, stats.rotations, stats.allocations), (3, 1, 3));
+    ///
+    /// tree.reset_stats();
+    /// assert!(tree.contains(&3));
+    /// assert_eq!(tree.stats().comparisons, 2);
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        self.stats.snapshot()
+    }
+
+    /// Resets every counter of the tree to 0.
+    pub fn reset_stats(&mut self) {
+        self.stats = Counters::default();
+    }
+}
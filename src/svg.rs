@@ -0,0 +1,449 @@
+//! SVG drawings of trees, and recordings of the rotations rebalancing them.
+//!
+//! Nodes are laid out in order from left to right, one column each, and one row per level.
+
+use alloc::{format, string::String, vec::Vec};
+use core::{
+    borrow::Borrow,
+    fmt::{self, Write},
+    ops::Deref,
+    time::Duration,
+};
+
+use crate::{
+    BST, Tree,
+    compare::Compare,
+    tree::{Observer, Rotation, Side},
+};
+
+/// The horizontal distance between two columns.
+const SPACING: usize = 40;
+/// The vertical distance between two levels.
+const LEVEL_HEIGHT: usize = 60;
+/// The radius of the circle around a node.
+const RADIUS: usize = 16;
+/// The empty space around the drawing.
+const MARGIN: usize = 30;
+/// The height of the caption above a frame.
+const CAPTION_HEIGHT: usize = 30;
+
+/// The coordinates of a node in a drawing.
+type Point = (usize, usize);
+
+/// The state of a tree right before or right after a rotation, recorded by a [`Recording`].
+#[derive(Clone, Debug)]
+pub struct Frame<T> {
+    /// The whole tree, with the rotated subtree in place.
+    pub tree: Tree<T>,
+    /// The rotation.
+    pub rotation: Rotation,
+    /// The value at the root of the rotated subtree, before the rotation.
+    pub pivot: T,
+    /// Whether the frame comes before the rotation, rather than after it.
+    pub before: bool,
+}
+
+impl<T> Frame<T>
+where
+    T: fmt::Display,
+{
+    /// Draws the frame as an SVG image, captioned with the rotation.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        let (width, height) = size(&self.tree, true);
+        write_header(&mut out, width, height);
+        write_frame(&mut out, self, None);
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+/// A series of changes to a [`BST`], recording a [`Frame`] before and after each rotation
+/// they make.
+///
+/// Created by [`BST::record`]. It dereferences to the tree, so every read sees the changes
+/// made so far.
+pub struct Recording<'a, T, C> {
+    tree: &'a mut BST<T, C>,
+    frames: Vec<Frame<T>>,
+}
+
+impl<'a, T, C> Recording<'a, T, C> {
+    pub(crate) fn new(tree: &'a mut BST<T, C>) -> Self {
+        Self {
+            tree,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Inserts a value into the tree, as [`BST::insert`] does, recording its rotations.
+    pub fn insert(&mut self, val: T)
+    where
+        T: Clone,
+        C: Compare<T>,
+    {
+        self.tree.cloner.record();
+        let recorder = Recorder::new(&self.tree.root, &mut self.frames);
+        self.tree.root.insert_tracked(
+            val,
+            &self.tree.stats.counted(&self.tree.cmp),
+            None,
+            T::clone,
+            &mut (&self.tree.stats, recorder),
+        );
+        self.tree.debug_check_invariants();
+    }
+
+    /// Removes a value from the tree, as [`BST::remove`] does, recording its rotations.
+    pub fn remove<Q>(&mut self, val: &Q)
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+    {
+        self.tree.cloner.record();
+        let recorder = Recorder::new(&self.tree.root, &mut self.frames);
+        self.tree.root.remove(
+            val,
+            &self.tree.stats.counted(&self.tree.cmp),
+            T::clone,
+            &mut (&self.tree.stats, recorder),
+        );
+        self.tree.debug_check_invariants();
+    }
+
+    /// Returns the frames recorded so far, in order.
+    pub fn frames(&self) -> &[Frame<T>] {
+        &self.frames
+    }
+
+    /// Draws each frame recorded so far as an SVG image, in order.
+    pub fn svg_frames(&self) -> Vec<String>
+    where
+        T: fmt::Display,
+    {
+        self.frames.iter().map(Frame::to_svg).collect()
+    }
+
+    /// Draws the frames recorded so far as a single animated SVG image, showing each one for
+    /// `frame_duration` in a loop.
+    pub fn to_animated_svg(&self, frame_duration: Duration) -> String
+    where
+        T: fmt::Display,
+    {
+        let (width, height) = self
+            .frames
+            .iter()
+            .map(|frame| size(&frame.tree, true))
+            .fold((0, 0), |(w1, h1), (w2, h2)| (w1.max(w2), h1.max(h2)));
+        let mut out = String::new();
+        write_header(&mut out, width, height);
+        let count = self.frames.len();
+        for (i, frame) in self.frames.iter().enumerate() {
+            let timing = (count > 1).then_some((i, count, frame_duration));
+            write_frame(&mut out, frame, timing);
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Writes each frame recorded so far to its own SVG file in `dir`, numbered in order from
+    /// `frame-0001.svg`.
+    #[cfg(feature = "std")]
+    pub fn write_svg_frames<P>(&self, dir: P) -> std::io::Result<()>
+    where
+        T: fmt::Display,
+        P: AsRef<std::path::Path>,
+    {
+        for (i, svg) in self.svg_frames().iter().enumerate() {
+            let path = dir.as_ref().join(format!("frame-{:04}.svg", i + 1));
+            std::fs::write(path, svg)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, C> Deref for Recording<'_, T, C> {
+    type Target = BST<T, C>;
+
+    fn deref(&self) -> &Self::Target {
+        self.tree
+    }
+}
+
+/// Helper struct following an operation down the tree, to record its rotations as frames.
+struct Recorder<'f, T> {
+    /// The tree before the operation.
+    root: Tree<T>,
+    /// The way from the root to the current node.
+    path: Vec<Side>,
+    /// The value at the root of the subtree being rotated.
+    pivot: Option<T>,
+    frames: &'f mut Vec<Frame<T>>,
+}
+
+impl<'f, T> Recorder<'f, T>
+where
+    T: Clone,
+{
+    fn new(root: &Tree<T>, frames: &'f mut Vec<Frame<T>>) -> Self {
+        Self {
+            root: root.shallow_copy(T::clone),
+            path: Vec::new(),
+            pivot: None,
+            frames,
+        }
+    }
+
+    /// Helper function to rebuild the whole tree with `subtree` at the current node.
+    fn splice(tree: &Tree<T>, path: &[Side], subtree: &Tree<T>) -> Tree<T> {
+        let (
+            Some((side, path)),
+            Tree::Node {
+                left, value, right, ..
+            },
+        ) = (path.split_first(), tree)
+        else {
+            return subtree.shallow_copy(T::clone);
+        };
+        match side {
+            Side::Left => Tree::node(
+                Self::splice(left, path, subtree),
+                value.clone(),
+                right.shallow_copy(T::clone),
+            ),
+            Side::Right => Tree::node(
+                left.shallow_copy(T::clone),
+                value.clone(),
+                Self::splice(right, path, subtree),
+            ),
+        }
+    }
+
+    fn record(&mut self, rotation: Rotation, tree: &Tree<T>, before: bool) {
+        if before {
+            self.pivot = tree.root_value().cloned();
+        }
+        let Some(pivot) = self.pivot.clone() else {
+            return;
+        };
+        self.frames.push(Frame {
+            tree: Self::splice(&self.root, &self.path, tree),
+            rotation,
+            pivot,
+            before,
+        });
+    }
+}
+
+impl<T> Observer<T> for Recorder<'_, T>
+where
+    T: Clone,
+{
+    fn descend(&mut self, _value: &T, side: Side) {
+        self.path.push(side);
+    }
+
+    fn ascend(&mut self) {
+        self.path.pop();
+    }
+
+    fn before_rotation(&mut self, rotation: Rotation, tree: &Tree<T>) {
+        self.record(rotation, tree, true);
+    }
+
+    fn after_rotation(&mut self, rotation: Rotation, tree: &Tree<T>) {
+        self.record(rotation, tree, false);
+    }
+}
+
+/// Helper function to compute the size of the drawing of a tree, with room for a caption.
+fn size<T>(tree: &Tree<T>, caption: bool) -> (usize, usize) {
+    let columns = tree.count_nodes().max(1);
+    let levels = tree.depth().max(1);
+    let top = if caption { CAPTION_HEIGHT } else { 0 };
+    (
+        2 * MARGIN + (columns - 1) * SPACING,
+        top + 2 * MARGIN + (levels - 1) * LEVEL_HEIGHT,
+    )
+}
+
+fn write_header(out: &mut String, width: usize, height: usize) {
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"sans-serif\" font-size=\"14\">"
+    )
+    .expect("writing to a string can't fail");
+}
+
+/// Helper function to write a frame in a group. If `timing` gives its index, the number of
+/// frames and how long each one shows, the group is only visible at its turn in the loop.
+fn write_frame<T>(out: &mut String, frame: &Frame<T>, timing: Option<(usize, usize, Duration)>)
+where
+    T: fmt::Display,
+{
+    let mut caption = String::new();
+    let stage = if frame.before { "before" } else { "after" };
+    write!(caption, "{stage} {} at {}", frame.rotation, frame.pivot)
+        .expect("writing to a string can't fail");
+
+    match timing {
+        None => out.push_str("  <g>\n"),
+        Some((i, count, frame_duration)) => {
+            // The frame shows from i / count to (i + 1) / count of the loop
+            let (start, end) = (i as f64 / count as f64, (i + 1) as f64 / count as f64);
+            let (values, key_times) = if i == 0 {
+                (String::from("visible;hidden"), format!("0;{end}"))
+            } else if i + 1 == count {
+                (String::from("hidden;visible"), format!("0;{start}"))
+            } else {
+                (
+                    String::from("hidden;visible;hidden"),
+                    format!("0;{start};{end}"),
+                )
+            };
+            let total = frame_duration.as_secs_f64() * count as f64;
+            writeln!(
+                out,
+                "  <g visibility=\"hidden\">\n    <animate attributeName=\"visibility\" \
+                 values=\"{values}\" keyTimes=\"{key_times}\" calcMode=\"discrete\" \
+                 dur=\"{total}s\" repeatCount=\"indefinite\"/>"
+            )
+            .expect("writing to a string can't fail");
+        }
+    }
+    out.push_str("    <text x=\"");
+    write!(out, "{MARGIN}\" y=\"{}\">", CAPTION_HEIGHT - 10)
+        .expect("writing to a string can't fail");
+    write_escaped(out, &caption);
+    out.push_str("</text>\n");
+    write_tree(out, &frame.tree, CAPTION_HEIGHT);
+    out.push_str("  </g>\n");
+}
+
+/// Helper function to write the edges, then the nodes of a tree drawn `top` pixels down.
+fn write_tree<T>(out: &mut String, tree: &Tree<T>, top: usize)
+where
+    T: fmt::Display,
+{
+    /// Lays out the nodes of a subtree in order, returning the position of its root.
+    fn layout<'a, T>(
+        tree: &'a Tree<T>,
+        level: usize,
+        top: usize,
+        column: &mut usize,
+        edges: &mut Vec<(Point, Point)>,
+        nodes: &mut Vec<(Point, &'a T)>,
+    ) -> Option<Point> {
+        let Tree::Node {
+            left, value, right, ..
+        } = tree
+        else {
+            return None;
+        };
+        let left = layout(left, level + 1, top, column, edges, nodes);
+        let pos = (
+            MARGIN + *column * SPACING,
+            top + MARGIN + level * LEVEL_HEIGHT,
+        );
+        *column += 1;
+        let right = layout(right, level + 1, top, column, edges, nodes);
+        edges.extend(left.into_iter().chain(right).map(|child| (pos, child)));
+        nodes.push((pos, value));
+        Some(pos)
+    }
+
+    let (mut edges, mut nodes) = (Vec::new(), Vec::new());
+    layout(tree, 0, top, &mut 0, &mut edges, &mut nodes);
+    for ((x1, y1), (x2, y2)) in edges {
+        writeln!(
+            out,
+            "    <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"black\"/>"
+        )
+        .expect("writing to a string can't fail");
+    }
+    for ((x, y), value) in nodes {
+        writeln!(
+            out,
+            "    <circle cx=\"{x}\" cy=\"{y}\" r=\"{RADIUS}\" fill=\"white\" stroke=\"black\"/>"
+        )
+        .expect("writing to a string can't fail");
+        write!(
+            out,
+            "    <text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" dominant-baseline=\"central\">"
+        )
+        .expect("writing to a string can't fail");
+        write_escaped(out, &format!("{value}"));
+        out.push_str("</text>\n");
+    }
+}
+
+/// Helper function to write text escaped for XML.
+fn write_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Draws the tree as an SVG image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let tree: BST<_> = BST::from_sorted(vec![1, 2, 3]);
+    /// let svg = tree.to_svg();
+    /// assert!(svg.starts_with("<svg"));
+    /// assert_eq!(svg.matches("<circle").count(), 3);
+    /// assert_eq!(svg.matches("<line").count(), 2);
+    /// ```
+    pub fn to_svg(&self) -> String
+    where
+        T: fmt::Display,
+    {
+        let mut out = String::new();
+        let (width, height) = size(&self.root, false);
+        write_header(&mut out, width, height);
+        out.push_str("  <g>\n");
+        write_tree(&mut out, &self.root, 0);
+        out.push_str("  </g>\n</svg>\n");
+        out
+    }
+
+    /// Starts recording the changes made to the tree, capturing a frame before and after each
+    /// rotation they make.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, Rotation};
+    /// use std::time::Duration;
+    ///
+    /// let mut tree = BST::new();
+    /// let mut recording = tree.record();
+    /// for i in [1, 2, 3] {
+    ///     recording.insert(i);
+    /// }
+    /// let frames = recording.frames();
+    /// assert_eq!(frames.len(), 2);
+    /// assert_eq!((frames[0].rotation, frames[0].pivot), (Rotation::Left, 1));
+    /// assert_eq!(frames[0].tree.root_value(), Some(&1));
+    /// assert_eq!(frames[1].tree.root_value(), Some(&2));
+    ///
+    /// let animation = recording.to_animated_svg(Duration::from_secs(1));
+    /// assert_eq!(animation.matches("<animate").count(), 2);
+    /// assert!(animation.contains("after rotate_left at 1"));
+    /// ```
+    pub fn record(&mut self) -> Recording<'_, T, C> {
+        Recording::new(self)
+    }
+}
@@ -1,6 +1,11 @@
 extern crate std;
 
-use crate::BST;
+use crate::{
+    BST, BSTMultiMap, BSTMultiset, InvariantError, Natural, PersistentBST, Subtree, Tree,
+    VersionedBST,
+    persistent::{ArcKind, RcKind, SharedKind},
+};
+use rand::Rng;
 
 #[test]
 fn test_count_and_depth() {
@@ -37,6 +42,40 @@ fn test_find() {
     assert_eq!(tree.find(&7).unwrap().root_value().unwrap(), &7);
     assert_eq!(tree.find(&5).unwrap().root_value().unwrap(), &5);
     assert!(tree.find(&999).is_none());
+
+    assert_eq!(tree.get(&2), Some(&2));
+    assert_eq!(tree.get(&7), Some(&7));
+    assert_eq!(tree.get(&5), Some(&5));
+    assert!(tree.get(&999).is_none());
+}
+
+#[test]
+fn test_get_or_insert() {
+    let mut tree = BST::new();
+
+    // Every insertion order below triggers a different rotation somewhere
+    for val in [10, 5, 15, 2, 7, 12, 18, 1, 6, 8, 9, 3, 4, 20, 19, 11] {
+        assert_eq!(tree.get_or_insert(val), &val);
+        dbg!(&tree);
+    }
+    for val in [10, 1, 20, 9] {
+        assert_eq!(tree.get_or_insert(val), &val);
+    }
+    assert_eq!(tree.count_nodes(), 16);
+    assert!(tree.balance_factor().abs() <= 1);
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..1000 {
+        let val = rng.gen_range(0..500);
+        assert_eq!(tree.get_or_insert(val), &val);
+    }
+
+    let mut names: BST<std::string::String> = BST::new();
+    assert_eq!(names.get_or_insert_with("b", str::to_owned), "b");
+    assert_eq!(names.get_or_insert_with("a", str::to_owned), "a");
+    assert_eq!(names.get_or_insert_with("c", str::to_owned), "c");
+    assert_eq!(names.get_or_insert_with("a", |_| unreachable!()), "a");
+    assert_eq!(names.count_nodes(), 3);
 }
 
 #[test]
@@ -51,8 +90,8 @@ fn test_ends() {
 
     dbg!(&tree);
 
-    assert_eq!(tree.left_end().unwrap().root_value().unwrap(), &2);
-    assert_eq!(tree.right_end().unwrap().root_value().unwrap(), &9);
+    assert_eq!(tree.root().left_end().unwrap().root_value().unwrap(), &2);
+    assert_eq!(tree.root().right_end().unwrap().root_value().unwrap(), &9);
 }
 
 #[test]
@@ -143,8 +182,8 @@ fn test_insert_maintains_balance() {
     //   / \
     //  2   5
     assert_eq!(tree.root_value(), Some(&3));
-    assert_eq!(tree.left().unwrap().root_value(), Some(&2));
-    assert_eq!(tree.right().unwrap().root_value(), Some(&5));
+    assert_eq!(tree.root().left().unwrap().root_value(), Some(&2));
+    assert_eq!(tree.root().right().unwrap().root_value(), Some(&5));
 
     //      2
     //       \
@@ -163,8 +202,8 @@ fn test_insert_maintains_balance() {
     //   / \
     //  2   6
     assert_eq!(tree2.root_value(), Some(&5));
-    assert_eq!(tree2.left().unwrap().root_value(), Some(&2));
-    assert_eq!(tree2.right().unwrap().root_value(), Some(&6));
+    assert_eq!(tree2.root().left().unwrap().root_value(), Some(&2));
+    assert_eq!(tree2.root().right().unwrap().root_value(), Some(&6));
 
     //      5
     //     /
@@ -183,8 +222,8 @@ fn test_insert_maintains_balance() {
     //   / \
     //  2   5
     assert_eq!(tree3.root_value(), Some(&3));
-    assert_eq!(tree3.left().unwrap().root_value(), Some(&2));
-    assert_eq!(tree3.right().unwrap().root_value(), Some(&5));
+    assert_eq!(tree3.root().left().unwrap().root_value(), Some(&2));
+    assert_eq!(tree3.root().right().unwrap().root_value(), Some(&5));
 
     //      2
     //       \
@@ -203,8 +242,8 @@ fn test_insert_maintains_balance() {
     //   / \
     //  2   5
     assert_eq!(tree4.root_value(), Some(&3));
-    assert_eq!(tree4.left().unwrap().root_value(), Some(&2));
-    assert_eq!(tree4.right().unwrap().root_value(), Some(&5));
+    assert_eq!(tree4.root().left().unwrap().root_value(), Some(&2));
+    assert_eq!(tree4.root().right().unwrap().root_value(), Some(&5));
 }
 
 #[test]
@@ -280,3 +319,1527 @@ fn test_depth() {
 
     assert!(tree.balance_factor().abs() <= 1);
 }
+
+#[test]
+fn test_modify() {
+    #[derive(Clone, Debug)]
+    struct Entry {
+        key: u32,
+        hits: u32,
+    }
+
+    impl PartialEq for Entry {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+    impl Eq for Entry {}
+    impl PartialOrd for Entry {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl Ord for Entry {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    let mut tree = BST::new();
+    for key in [10, 5, 15, 2, 7, 12, 18] {
+        tree.insert(Entry { key, hits: 0 });
+    }
+    let probe = |key| Entry { key, hits: 0 };
+
+    // Changing a field that doesn't affect the order keeps the value in place
+    let root = tree.root_value().unwrap().key;
+    assert!(tree.modify(&probe(root), |e| e.hits += 1));
+    assert_eq!(tree.root_value().unwrap().key, root);
+    assert_eq!(tree.get(&probe(root)).unwrap().hits, 1);
+
+    // Moving the key past its neighbours repositions it
+    assert!(tree.modify(&probe(7), |e| e.key = 20));
+    dbg!(&tree);
+    assert!(!tree.contains(&probe(7)));
+    assert!(tree.contains(&probe(20)));
+    assert_eq!(tree.count_nodes(), 7);
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    // Moving it onto an existing key drops it
+    assert!(tree.modify(&probe(20), |e| e.key = 2));
+    assert_eq!(tree.count_nodes(), 6);
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    assert!(!tree.modify(&probe(999), |_| unreachable!()));
+
+    let mut rng = rand::thread_rng();
+    let mut tree = BST::new();
+    for val in 0..200 {
+        tree.insert(val * 2);
+    }
+    for _ in 0..500 {
+        let from = rng.gen_range(0..200) * 2;
+        let to = rng.gen_range(0..200) * 2 + 1;
+        if tree.contains(&from) {
+            tree.modify(&from, |v| *v = to);
+            assert!(tree.contains(&to));
+        }
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+}
+
+#[test]
+fn test_check_invariants() {
+    let mut tree = BST::new();
+    for val in [10, 5, 15] {
+        tree.insert(val);
+    }
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    let mut unordered = tree.clone();
+    if let Tree::Node { value, .. } = &mut unordered.root {
+        *value = 100;
+    }
+    assert_eq!(unordered.check_invariants(), Err(InvariantError::Unordered));
+
+    let mut wrong_depth = tree.clone();
+    if let Tree::Node { depth, .. } = &mut wrong_depth.root {
+        *depth = 5;
+    }
+    assert_eq!(
+        wrong_depth.check_invariants(),
+        Err(InvariantError::WrongDepth)
+    );
+}
+
+#[test]
+fn test_comparators() {
+    // Reverse order puts the largest value at the left end
+    let mut tree = BST::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    for val in [5, 8, 3, 2, 9, 7] {
+        tree.insert(val);
+    }
+    dbg!(&tree);
+    assert_eq!(tree.root().left_end().unwrap().root_value(), Some(&9));
+    assert_eq!(tree.root().right_end().unwrap().root_value(), Some(&2));
+    assert_eq!(tree.check_invariants(), Ok(()));
+    tree.remove(&8);
+    assert!(!tree.contains(&8));
+    assert!(tree.modify(&3, |v| *v = 10));
+    assert_eq!(tree.root().left_end().unwrap().root_value(), Some(&10));
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    // Sorting by a projected key treats values with the same key as equal
+    let mut tree = BST::by_key(|s: &std::string::String| s.to_lowercase());
+    for name in ["Bob", "alice", "ALICE", "carol", "bob"] {
+        tree.insert(name.into());
+    }
+    assert_eq!(tree.count_nodes(), 3);
+    assert_eq!(tree.get(&"BOB".into()).map(|s| s.as_str()), Some("Bob"));
+    tree.remove(&"Carol".into());
+    assert_eq!(tree.count_nodes(), 2);
+    assert_eq!(
+        tree.root().right_end().unwrap().root_value().unwrap(),
+        "Bob"
+    );
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn test_iter() {
+    let mut tree = BST::new();
+    assert_eq!(tree.iter().next(), None);
+
+    let mut expected = std::vec::Vec::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        let val = rng.gen_range(0..100);
+        tree.insert(val);
+        expected.push(val);
+    }
+    expected.sort();
+    expected.dedup();
+    assert!(tree.iter().eq(expected.iter()));
+}
+
+#[test]
+fn test_multiset() {
+    // Only the multiset's nodes cache counts and sizes: a plain node holds its children, its
+    // value and its depth
+    assert_eq!(
+        core::mem::size_of::<Tree<u64>>(),
+        3 * core::mem::size_of::<usize>() + core::mem::size_of::<u64>()
+    );
+
+    let mut set = BSTMultiset::new();
+    let mut expected = std::vec::Vec::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..300 {
+        let val = rng.gen_range(0..30);
+        if rng.gen_bool(0.3) {
+            let found = expected.iter().position(|&v| v == val);
+            assert_eq!(set.remove_one(&val), found.is_some());
+            if let Some(index) = found {
+                expected.remove(index);
+            }
+        } else {
+            set.insert(val);
+            expected.push(val);
+        }
+        assert_eq!(set.check_invariants(), Ok(()));
+    }
+    expected.sort();
+
+    dbg!(&set);
+    assert_eq!(set.len(), expected.len());
+    assert!(set.iter().eq(expected.iter()));
+    for val in 0..30 {
+        let count = expected.iter().filter(|&&v| v == val).count();
+        assert_eq!(set.count(&val), count);
+        assert_eq!(set.rank(&val), expected.partition_point(|&v| v < val));
+    }
+    for (index, val) in expected.iter().enumerate() {
+        assert_eq!(set.select(index), Some(val));
+    }
+    assert_eq!(set.select(expected.len()), None);
+
+    let first = expected[0];
+    let removed = set.remove_all(&first);
+    expected.retain(|&v| v != first);
+    assert!(removed > 0);
+    assert!(set.iter().eq(expected.iter()));
+    assert_eq!(set.len(), expected.len());
+
+    // The counts cached in the nodes a clone shares are copied before changing them
+    let copy = set.clone();
+    let last = *expected.last().unwrap();
+    set.insert(last);
+    set.remove_one(&expected[0]);
+    assert!(copy.iter().eq(expected.iter()));
+    assert_eq!(copy.rank(&last), expected.len() - copy.count(&last));
+    assert_eq!(set.count(&last), copy.count(&last) + 1);
+    assert_eq!(set.len(), expected.len());
+    assert_eq!(set.check_invariants(), Ok(()));
+}
+
+#[test]
+fn test_range() {
+    use core::ops::Bound;
+
+    let mut tree = BST::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        tree.insert(rng.gen_range(0..50) * 2);
+    }
+    let values: std::vec::Vec<_> = tree.iter().copied().collect();
+
+    for _ in 0..300 {
+        let mut bound = || match rng.gen_range(0..3) {
+            0 => Bound::Included(rng.gen_range(-5..105)),
+            1 => Bound::Excluded(rng.gen_range(-5..105)),
+            _ => Bound::Unbounded,
+        };
+        let range = (bound(), bound());
+        let expected = values
+            .iter()
+            .filter(|&v| core::ops::RangeBounds::contains(&range, v));
+        assert!(tree.range(range).eq(expected), "range {:?}", range);
+    }
+}
+
+#[test]
+fn test_multimap() {
+    let mut map = BSTMultiMap::new();
+    for (time, event) in [
+        (5, "e"),
+        (1, "a"),
+        (3, "c1"),
+        (3, "c2"),
+        (8, "h"),
+        (3, "c3"),
+    ] {
+        map.insert(time, event);
+    }
+    dbg!(&map);
+
+    assert_eq!(map.len(), 6);
+    assert_eq!(map.key_count(), 4);
+    assert!(map.get_all(&3).eq(&["c1", "c2", "c3"]));
+    assert_eq!(map.get_all(&4).next(), None);
+    assert!(map.keys().eq(&[1, 3, 5, 8]));
+    assert!(
+        map.range(2..=5)
+            .eq([(&3, &"c1"), (&3, &"c2"), (&3, &"c3"), (&5, &"e")])
+    );
+
+    assert_eq!(map.remove_one(&3), Some("c3"));
+    assert_eq!(map.remove_all(&3), ["c1", "c2"]);
+    assert_eq!(map.remove_one(&3), None);
+    assert!(map.remove_all(&3).is_empty());
+    assert_eq!(map.len(), 3);
+    assert!(map.iter().eq([(&1, &"a"), (&5, &"e"), (&8, &"h")]));
+
+    assert_eq!(map.remove_one(&1), Some("a"));
+    assert!(!map.contains_key(&1));
+    assert_eq!(map.key_count(), 2);
+}
+
+#[test]
+fn test_persistent() {
+    fn check<P: SharedKind>() {
+        let mut versions = std::vec![PersistentBST::<i32, Natural, P>::new()];
+        let mut models = std::vec![std::collections::BTreeSet::new()];
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let val = rng.gen_range(0..100);
+            let (last, mut model) = (versions.last().unwrap(), models.last().unwrap().clone());
+            let next = if rng.gen_bool(0.3) {
+                model.remove(&val);
+                last.remove(&val)
+            } else {
+                model.insert(val);
+                last.insert(val)
+            };
+            assert!(next.is_balanced());
+            versions.push(next);
+            models.push(model);
+        }
+
+        // Every old version is still intact
+        for (version, model) in versions.iter().zip(&models) {
+            assert!(version.iter().eq(model.iter()));
+            assert_eq!(version.count_nodes(), model.len());
+        }
+    }
+    check::<RcKind>();
+    check::<ArcKind>();
+
+    // A new version only copies the path to the change
+    let mut v1: PersistentBST<i32> = PersistentBST::new();
+    for val in 0..127 {
+        v1 = v1.insert(val);
+    }
+    assert_eq!(v1.depth(), 7);
+    let v2 = v1.insert(1000);
+    assert_eq!(v2.shared_nodes(&v1), 127 - 7);
+    let v3 = v1.remove(&0);
+    assert_eq!(v3.shared_nodes(&v1), 127 - 7);
+    assert!(v1.remove(&1000).ptr_eq(&v1));
+    assert_eq!(v1.count_nodes(), 127);
+}
+
+#[test]
+fn test_clone_shares_nodes() {
+    fn children<T>(tree: &BST<T>) -> (&Subtree<T>, &Subtree<T>) {
+        match tree.root() {
+            Tree::Node { left, right, .. } => (left, right),
+            Tree::Empty => panic!("tree is empty"),
+        }
+    }
+
+    let mut tree = BST::new();
+    for i in 0..127 {
+        tree.insert(i);
+    }
+
+    let mut copy = tree.clone();
+    assert!(Subtree::ptr_eq(children(&tree).0, children(&copy).0));
+    assert!(Subtree::ptr_eq(children(&tree).1, children(&copy).1));
+
+    // Changing the copy only copies the path to the change
+    copy.insert(1000);
+    copy.remove(&0);
+    copy.modify(&100, |v| *v = 101);
+    assert!(!Subtree::ptr_eq(children(&tree).0, children(&copy).0));
+    assert!(!Subtree::ptr_eq(children(&tree).1, children(&copy).1));
+    assert_eq!(copy.check_invariants(), Ok(()));
+
+    assert!(tree.iter().copied().eq(0..127));
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert!(
+        copy.iter()
+            .copied()
+            .eq((1..127).filter(|&v| v != 100).chain([1000]))
+    );
+
+    // Missing values to remove and existing values to insert don't copy anything
+    let copy = tree.clone();
+    tree.remove(&1000);
+    assert!(!tree.modify(&1000, |_| unreachable!()));
+    tree.insert(5);
+    assert_eq!(tree.get_or_insert(120), &120);
+    assert!(Subtree::ptr_eq(children(&tree).0, children(&copy).0));
+    assert!(Subtree::ptr_eq(children(&tree).1, children(&copy).1));
+
+    // Values that can't be cloned can still be inserted and removed
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct NoClone(i32);
+    let mut tree = BST::new();
+    for i in 0..20 {
+        tree.insert(NoClone(i));
+    }
+    assert_eq!(tree.get_or_insert(NoClone(3)), &NoClone(3));
+    assert!(tree.modify(&NoClone(4), |v| v.0 = 40));
+    tree.remove(&NoClone(5));
+    assert_eq!(tree.count_nodes(), 19);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn test_versioned() {
+    use std::{cell::Cell, collections::BTreeSet, vec::Vec};
+
+    let mut rng = rand::thread_rng();
+    let mut tree = VersionedBST::new();
+    let mut models = Vec::from([BTreeSet::new()]);
+    let mut model = BTreeSet::new();
+    for _ in 0..20 {
+        for _ in 0..rng.gen_range(0..30) {
+            let val = rng.gen_range(0..200);
+            if rng.gen_bool(0.6) {
+                tree.insert(val);
+                model.insert(val);
+            } else {
+                tree.remove(&val);
+                model.remove(&val);
+            }
+        }
+        assert_eq!(tree.commit(), models.len());
+        models.push(model.clone());
+    }
+
+    for (version, model) in models.iter().enumerate() {
+        let past = tree.at_version(version).unwrap();
+        assert!(past.iter().eq(model));
+        assert_eq!(past.check_invariants(), Ok(()));
+    }
+    for _ in 0..50 {
+        let from = rng.gen_range(0..models.len());
+        let to = rng.gen_range(0..models.len());
+        let diff = tree.diff(from, to).unwrap();
+        assert!(
+            diff.added
+                .into_iter()
+                .eq(models[to].difference(&models[from]))
+        );
+        assert!(
+            diff.removed
+                .into_iter()
+                .eq(models[from].difference(&models[to]))
+        );
+    }
+    assert!(tree.at_version(models.len()).is_none());
+    assert!(tree.diff(0, models.len()).is_none());
+
+    tree.keep_last(5);
+    assert_eq!(tree.oldest_version(), models.len() - 5);
+    assert!(tree.at_version(models.len() - 6).is_none());
+    assert!(
+        tree.at_version(models.len() - 5)
+            .unwrap()
+            .iter()
+            .eq(&models[models.len() - 5])
+    );
+
+    // Diffing a small change to a big tree skips the subtrees both versions share
+    let comparisons = Cell::new(0);
+    let mut tree = VersionedBST::with_comparator(|a: &i32, b: &i32| {
+        comparisons.set(comparisons.get() + 1);
+        a.cmp(b)
+    });
+    for i in 0..1000 {
+        tree.insert(i * 2);
+    }
+    let before = tree.commit();
+    tree.insert(501);
+    tree.remove(&1200);
+    let after = tree.commit();
+    comparisons.set(0);
+    let diff = tree.diff(before, after).unwrap();
+    assert_eq!(diff.added, [&501]);
+    assert_eq!(diff.removed, [&1200]);
+    assert!(comparisons.get() < 100, "{} comparisons", comparisons.get());
+}
+
+#[test]
+fn test_transaction() {
+    use std::{collections::BTreeSet, vec::Vec};
+
+    let mut rng = rand::thread_rng();
+    let mut tree = BST::new();
+    let mut model = BTreeSet::new();
+    for _ in 0..200 {
+        let ops: Vec<(bool, i32)> = (0..rng.gen_range(1..20))
+            .map(|_| (rng.gen_bool(0.7), rng.gen_range(0..100)))
+            .collect();
+        let fail = rng.gen_bool(0.3);
+        let res = tree.transaction(|txn| {
+            let mut batch = model.clone();
+            for &(insert, val) in &ops {
+                if insert {
+                    txn.insert(val);
+                    batch.insert(val);
+                } else {
+                    txn.remove(&val);
+                    batch.remove(&val);
+                }
+                // Reads see the batch's own writes
+                assert_eq!(txn.contains(&val), insert);
+            }
+            assert!(txn.iter().eq(&batch));
+            if fail { Err(()) } else { Ok(batch) }
+        });
+        assert_eq!(res.is_err(), fail);
+        if let Ok(batch) = res {
+            model = batch;
+        }
+        assert!(tree.iter().eq(&model));
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    // A batch panicking halfway is rolled back too
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        tree.transaction(|txn| -> Result<(), ()> {
+            txn.clear();
+            txn.insert(1000);
+            panic!("batch failed")
+        })
+    }));
+    assert!(res.is_err());
+    assert!(tree.iter().eq(&model));
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_concurrent() {
+    use crate::ConcurrentBST;
+    use std::{sync::Arc, thread, vec::Vec};
+
+    let tree = Arc::new(ConcurrentBST::new());
+    let writers: Vec<_> = (0..4)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for i in 0..250 {
+                    // Publish pairs of values together
+                    let val = (t * 250 + i) * 2;
+                    tree.update(|tree| {
+                        tree.insert(val);
+                        tree.insert(val + 1);
+                    });
+                }
+                for i in (0..250).step_by(5) {
+                    tree.remove(&((t * 250 + i) * 2));
+                    tree.remove(&((t * 250 + i) * 2 + 1));
+                }
+            })
+        })
+        .collect();
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let snapshot = tree.snapshot();
+                    assert_eq!(snapshot.check_invariants(), Ok(()));
+                    assert!(snapshot.count_nodes() % 2 == 0);
+                    let mut values = snapshot.iter();
+                    while let (Some(&a), Some(&b)) = (values.next(), values.next()) {
+                        assert_eq!((a % 2, b), (0, a + 1));
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in writers.into_iter().chain(readers) {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(tree.count_nodes(), 4 * 200 * 2);
+    assert!(tree.contains(&2) && !tree.contains(&0));
+    assert_eq!(tree.get(&3), Some(3));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sharded() {
+    use crate::ShardedBSTMap;
+    use std::{collections::BTreeMap, sync::Arc, thread, vec::Vec};
+
+    let map = Arc::new(ShardedBSTMap::with_shard_size(32));
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let map = Arc::clone(&map);
+            thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                let mut model = BTreeMap::new();
+                // Each thread owns the keys equal to `t` modulo 8
+                for _ in 0..2000 {
+                    let key = rng.gen_range(0..100) * 8 + t;
+                    if rng.gen_bool(0.6) {
+                        let value = rng.r#gen::<u32>();
+                        assert_eq!(map.insert(key, value), model.insert(key, value));
+                    } else {
+                        assert_eq!(map.remove(&key), model.remove(&key));
+                    }
+                    assert_eq!(map.get(&key), model.get(&key).copied());
+                }
+                model
+            })
+        })
+        .collect();
+    let mut model = BTreeMap::new();
+    for handle in handles {
+        model.extend(handle.join().unwrap());
+    }
+
+    assert_eq!(map.len(), model.len());
+    assert!(map.shard_count() > 1);
+    assert!(map.range(..).into_iter().eq(model.clone()));
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let start = rng.gen_range(0..800);
+        let end = rng.gen_range(start..=800);
+        let expected: Vec<_> = model.range(start..end).map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(map.range(start..end), expected);
+        let expected: Vec<_> = model.range(start..=end).map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(map.range(start..=end), expected);
+    }
+
+    // Emptying the map merges the shards back
+    for key in model.keys() {
+        map.remove(key);
+    }
+    assert!(map.is_empty());
+    assert_eq!(map.shard_count(), 1);
+
+    // Merging a small shard with a full one splits them again, so no shard outgrows the size
+    let map = ShardedBSTMap::with_shard_size(8);
+    for key in 0..12 {
+        map.insert(key, ());
+    }
+    assert_eq!(map.shard_lens(), [4, 8]);
+    for key in 0..3 {
+        map.remove(&key);
+    }
+    assert_eq!(map.shard_lens(), [4, 5]);
+    assert!(map.range(..).into_iter().map(|(key, _)| key).eq(3..12));
+
+    // Shards are ordered by the comparator of the map, and none outgrows the size either way
+    let map = ShardedBSTMap::with_comparator_and_shard_size(|a: &i32, b: &i32| b.cmp(a), 8);
+    let mut model = BTreeMap::new();
+    for _ in 0..5000 {
+        let key = rng.gen_range(0..200);
+        if rng.gen_bool(0.55) {
+            assert_eq!(map.insert(key, key), model.insert(key, key));
+        } else {
+            assert_eq!(map.remove(&key), model.remove(&key));
+        }
+        assert!(map.shard_lens().iter().all(|&len| len <= 8));
+    }
+    assert_eq!(map.len(), model.len());
+    assert!(map.range(..).into_iter().eq(model.into_iter().rev()));
+
+    // A value panicking as it is copied out poisons its shard, which still works afterwards
+    #[derive(Debug, PartialEq)]
+    struct Fragile(bool);
+    impl Clone for Fragile {
+        fn clone(&self) -> Self {
+            assert!(!self.0, "fragile value copied");
+            Self(self.0)
+        }
+    }
+    let map = ShardedBSTMap::with_shard_size(4);
+    for key in 0..10 {
+        map.insert(key, Fragile(false));
+    }
+    map.insert(3, Fragile(true));
+    assert!(std::panic::catch_unwind(|| map.get(&3)).is_err());
+    assert_eq!(map.get(&4), Some(Fragile(false)));
+    assert_eq!(map.insert(3, Fragile(false)), Some(Fragile(true)));
+    assert_eq!(map.len(), 10);
+}
+
+#[test]
+fn test_set_operations() {
+    use std::{collections::BTreeSet, vec::Vec};
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        let a: BTreeSet<i32> = (0..rng.gen_range(0..300))
+            .map(|_| rng.gen_range(0..500))
+            .collect();
+        let b: BTreeSet<i32> = (0..rng.gen_range(0..300))
+            .map(|_| rng.gen_range(0..500))
+            .collect();
+        let tree_a: BST<_> = BST::from_sorted(a.iter().copied().collect());
+        let tree_b: BST<_> = BST::from_sorted(b.iter().copied().collect());
+        assert_eq!(tree_a.check_invariants(), Ok(()));
+
+        let union = tree_a.clone().union(tree_b.clone());
+        assert_eq!(union.check_invariants(), Ok(()));
+        assert!(union.iter().eq(a.union(&b)));
+        let intersection = tree_a.intersection(tree_b);
+        assert_eq!(intersection.check_invariants(), Ok(()));
+        assert!(intersection.iter().eq(a.intersection(&b)));
+    }
+
+    // Both operations keep the values of the first tree
+    let tree = BST::by_key(|&(key, _): &(i32, char)| key);
+    let a = [(1, 'a'), (2, 'a')]
+        .into_iter()
+        .fold(tree.clone(), |mut t, v| {
+            t.insert(v);
+            t
+        });
+    let b = [(2, 'b'), (3, 'b')].into_iter().fold(tree, |mut t, v| {
+        t.insert(v);
+        t
+    });
+    let union: Vec<_> = a.clone().union(b.clone()).iter().copied().collect();
+    assert_eq!(union, [(1, 'a'), (2, 'a'), (3, 'b')]);
+    let intersection: Vec<_> = a.intersection(b).iter().copied().collect();
+    assert_eq!(intersection, [(2, 'a')]);
+}
+
+#[test]
+fn test_shared_roots() {
+    use std::vec::Vec;
+
+    // Changes every value of a tree sharing nodes with `operand`, which must be left as it was
+    fn check(mut tree: BST<i32>, operand: &BST<i32>, expected: &[i32]) {
+        let before: Vec<_> = operand.iter().copied().collect();
+        assert!(tree.iter().eq(expected));
+        for &val in expected {
+            assert!(tree.modify(&val, |v| *v += 1000));
+            tree.remove(&(val + 1000));
+            tree.insert(val);
+        }
+        assert!(tree.iter().eq(expected));
+        assert_eq!(tree.check_invariants(), Ok(()));
+        assert!(operand.iter().eq(&before));
+    }
+
+    // The result is built out of trees which never shared their nodes, but for `other`
+    let a = || BST::from_sorted((0..100).collect());
+    let b = || BST::from_sorted((50..150).collect());
+    let (shared_a, shared_b) = (a(), b());
+    let expected: Vec<_> = (0..150).collect();
+    check(a().union(shared_b.clone()), &shared_b, &expected);
+    check(b().union(shared_a.clone()), &shared_a, &expected);
+    let expected: Vec<_> = (50..100).collect();
+    check(a().intersection(shared_b.clone()), &shared_b, &expected);
+    check(b().intersection(shared_a.clone()), &shared_a, &expected);
+    #[cfg(feature = "rayon")]
+    {
+        let expected: Vec<_> = (0..150).collect();
+        check(a().par_union(shared_b.clone()), &shared_b, &expected);
+        let expected: Vec<_> = (50..100).collect();
+        check(b().par_intersection(shared_a.clone()), &shared_a, &expected);
+    }
+
+    // Transactions change the tree while the snapshot to roll back to shares its nodes
+    let mut tree = a();
+    tree.transaction(|txn| {
+        txn.insert(200);
+        txn.remove(&0);
+        assert!(txn.modify(&50, |v| *v = 300));
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+    assert!(
+        (1..100)
+            .filter(|&v| v != 50)
+            .chain([200, 300])
+            .eq(tree.iter().copied())
+    );
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    // Shards split and merged share the nodes of the shards they came from
+    #[cfg(feature = "std")]
+    {
+        let map = crate::ShardedBSTMap::with_shard_size(8);
+        for round in 0..3 {
+            for key in 0..100 {
+                map.insert(key, round);
+            }
+            assert!(map.shard_count() > 1);
+            for key in (0..100).rev() {
+                assert_eq!(map.remove(&key), Some(round));
+            }
+            assert_eq!(map.shard_count(), 1);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "values are not sorted")]
+fn test_from_sorted_unsorted() {
+    let _: BST<_> = BST::from_sorted(std::vec![1, 3, 2]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_parallel() {
+    use rayon::prelude::*;
+    use std::vec::Vec;
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..5 {
+        let mut a: Vec<u32> = (0..rng.gen_range(0..20000))
+            .map(|_| rng.gen_range(0..50000))
+            .collect();
+        a.sort();
+        a.dedup();
+        let mut b: Vec<u32> = (0..rng.gen_range(0..20000))
+            .map(|_| rng.gen_range(0..50000))
+            .collect();
+        b.sort();
+        b.dedup();
+
+        let tree_a: BST<_> = BST::from_sorted(a.clone());
+        let tree_b: BST<_> = BST::from_sorted(b.clone());
+        let par_a: BST<_> = BST::par_from_sorted(a);
+        assert_eq!(par_a, tree_a);
+        assert_eq!(par_a.check_invariants(), Ok(()));
+
+        let par_values: Vec<_> = tree_a.par_iter().copied().collect();
+        assert!(tree_a.iter().copied().eq(par_values));
+        assert_eq!(
+            tree_a.par_iter().map(|&v| u64::from(v)).sum::<u64>(),
+            tree_a.iter().map(|&v| u64::from(v)).sum::<u64>()
+        );
+
+        let union = tree_a.clone().par_union(tree_b.clone());
+        assert_eq!(union.check_invariants(), Ok(()));
+        assert!(union.iter().eq(tree_a.clone().union(tree_b.clone()).iter()));
+        let intersection = tree_a.clone().par_intersection(tree_b.clone());
+        assert_eq!(intersection.check_invariants(), Ok(()));
+        assert!(intersection.iter().eq(tree_a.intersection(tree_b).iter()));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let tree: BST<_> = BST::from_sorted((0..100).collect());
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(
+        json,
+        serde_json::to_string(&(0..100).collect::<std::vec::Vec<_>>()).unwrap()
+    );
+
+    // Sorted input is built straight into a balanced tree
+    let back: BST<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, tree);
+    assert_eq!(back.check_invariants(), Ok(()));
+
+    // Anything else is inserted value by value
+    let unsorted: BST<i32> = serde_json::from_str("[5, 1, 4, 1, 3, 2]").unwrap();
+    assert!(unsorted.iter().eq(&[1, 2, 3, 4, 5]));
+    assert_eq!(unsorted.check_invariants(), Ok(()));
+
+    // Values don't need to be cloned to be deserialized
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct NoClone(i32);
+    impl<'de> serde::Deserialize<'de> for NoClone {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            i32::deserialize(deserializer).map(NoClone)
+        }
+    }
+    let no_clone: BST<NoClone> = serde_json::from_str("[3, 1, 2]").unwrap();
+    assert!(no_clone.iter().eq(&[NoClone(1), NoClone(2), NoClone(3)]));
+
+    let reversed: BST<i32, _> = BST::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    assert_eq!(serde_json::to_string(&reversed).unwrap(), "[]");
+    assert!(serde_json::from_str::<BST<i32>>("{}").is_err());
+}
+
+#[test]
+fn test_codec() {
+    use crate::codec::{Codec, DecodeError};
+    use std::{collections::BTreeSet, fmt::Debug, string::String, vec::Vec};
+
+    fn round_trip<T: Codec + Ord + Clone + Debug>(values: BTreeSet<T>) -> Vec<u8> {
+        let tree: BST<T> = BST::from_sorted(values.into_iter().collect());
+        let mut bytes = Vec::new();
+        tree.encode(&mut bytes).unwrap();
+        let decoded = BST::decode(&bytes).unwrap();
+        assert_eq!(decoded, tree);
+        assert_eq!(decoded.check_invariants(), Ok(()));
+        bytes
+    }
+
+    let mut rng = rand::thread_rng();
+    round_trip((0..1000).map(|_| rng.r#gen::<i64>()).collect());
+    round_trip((0..1000).map(|_| rng.r#gen::<u128>()).collect());
+    round_trip((0..=255).collect::<BTreeSet<u8>>());
+    round_trip([i8::MIN, -1, 0, 1, i8::MAX].into_iter().collect());
+    round_trip([false, true].into_iter().collect());
+    round_trip((0..100).map(|_| rng.r#gen::<char>()).collect());
+    round_trip(["", "a", "ab", "é"].into_iter().map(String::from).collect());
+    round_trip(BTreeSet::<u32>::new());
+
+    // Dense integers only store small differences
+    let bytes = round_trip((1_000_000..1_001_000).collect::<BTreeSet<u64>>());
+    assert!(bytes.len() < 1000 + 12, "{} bytes", bytes.len());
+
+    let tree: BST<u32> = BST::from_sorted((0..100).map(|i| i * 1000).collect());
+    let mut bytes = Vec::new();
+    tree.encode(&mut bytes).unwrap();
+    for len in 0..bytes.len() {
+        assert_eq!(
+            BST::<u32>::decode(&bytes[..len]),
+            Err(DecodeError::Truncated)
+        );
+    }
+    let mut corrupted = bytes.clone();
+    corrupted[10] ^= 0x01;
+    assert_eq!(
+        BST::<u32>::decode(&corrupted),
+        Err(DecodeError::ChecksumMismatch)
+    );
+    let mut corrupted = bytes.clone();
+    corrupted[0] = 2;
+    assert_eq!(
+        BST::<u32>::decode(&corrupted),
+        Err(DecodeError::UnsupportedVersion(2))
+    );
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_eq!(BST::<u32>::decode(&longer), Err(DecodeError::TrailingData));
+
+    let mut reversed = BST::with_comparator(|a: &u32, b: &u32| b.cmp(a));
+    for i in 0..10 {
+        reversed.insert(i);
+    }
+    let mut bytes = Vec::new();
+    reversed.encode(&mut bytes).unwrap();
+    assert_eq!(BST::<u32>::decode(&bytes), Err(DecodeError::Unsorted));
+}
+
+#[test]
+fn test_shape_dump() {
+    use crate::{codec::DecodeError, shape::ShapeDumpError};
+
+    let mut rng = rand::thread_rng();
+    let mut tree = BST::new();
+    for _ in 0..300 {
+        tree.insert(rng.gen_range(-1000..1000));
+    }
+    for _ in 0..100 {
+        tree.remove(&rng.gen_range(-1000..1000));
+    }
+
+    let text = tree.to_shape_dump();
+    let restored = BST::<i32>::from_shape_dump(&text).unwrap();
+    assert_eq!(restored.to_shape_dump(), text);
+    assert_eq!(restored.root(), tree.root());
+    let bytes = tree.to_shape_dump_bytes();
+    let restored = BST::<i32>::from_shape_dump_bytes(&bytes).unwrap();
+    assert_eq!(restored.to_shape_dump(), text);
+
+    let empty = BST::<i32>::new();
+    assert_eq!(empty.to_shape_dump(), "");
+    assert_eq!(BST::<i32>::from_shape_dump(""), Ok(empty.clone()));
+    assert_eq!(
+        BST::<i32>::from_shape_dump_bytes(&empty.to_shape_dump_bytes()),
+        Ok(empty)
+    );
+
+    // Trees breaking an invariant are rejected
+    let invariant = |err| Err(ShapeDumpError::Invariant(err));
+    let unordered = "[2] 2\n  L [1] 3\n";
+    assert_eq!(
+        BST::<i32>::from_shape_dump(unordered),
+        invariant(InvariantError::Unordered)
+    );
+    let unbalanced = "[3] 1\n  R [2] 2\n    R [1] 3\n";
+    assert_eq!(
+        BST::<i32>::from_shape_dump(unbalanced),
+        invariant(InvariantError::Unbalanced)
+    );
+    let wrong_depth = "[1] 1\n  R [1] 2\n";
+    assert_eq!(
+        BST::<i32>::from_shape_dump(wrong_depth),
+        invariant(InvariantError::WrongDepth)
+    );
+    let unbalanced: BST<i32> = BST::from_shape_dump("[2] 2\n  L [1] 1\n").unwrap();
+    let mut bytes = unbalanced.to_shape_dump_bytes();
+    // Version, root tag and depth, then the root value 2, made 0
+    bytes[3] = 0;
+    assert_eq!(
+        BST::<i32>::from_shape_dump_bytes(&bytes),
+        invariant(InvariantError::Unordered)
+    );
+
+    // And so are malformed dumps
+    let parse = |line| Err(ShapeDumpError::Parse { line });
+    assert_eq!(BST::<i32>::from_shape_dump("[1] x\n"), parse(1));
+    assert_eq!(BST::<i32>::from_shape_dump("[2] 2\n L [1] 1\n"), parse(2));
+    assert_eq!(
+        BST::<i32>::from_shape_dump("[2] 2\n  R [1] 3\n  L [1] 1\n"),
+        parse(3)
+    );
+    assert_eq!(BST::<i32>::from_shape_dump("[1] 1\n[1] 2\n"), parse(2));
+    let decode = |err| Err(ShapeDumpError::Decode(err));
+    let bytes = tree.to_shape_dump_bytes();
+    assert_eq!(
+        BST::<i32>::from_shape_dump_bytes(&bytes[..bytes.len() - 1]),
+        decode(DecodeError::Truncated)
+    );
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_eq!(
+        BST::<i32>::from_shape_dump_bytes(&longer),
+        decode(DecodeError::TrailingData)
+    );
+}
+
+#[test]
+fn test_dot() {
+    use crate::dot::DotOptions;
+    use std::string::{String, ToString};
+
+    let mut rng = rand::thread_rng();
+    let mut tree = BST::new();
+    for _ in 0..200 {
+        tree.insert(rng.gen_range(0..1000));
+    }
+    let dot = tree.to_dot();
+    assert!(dot.starts_with("digraph BST {\n") && dot.ends_with("}\n"));
+    let nodes = dot.matches("[label=").count();
+    let edges = dot.lines().filter(|l| l.contains("->")).count();
+    let invisible = dot.matches("[style=invis]").count();
+    assert_eq!(nodes, tree.count_nodes());
+    assert_eq!(edges - invisible, nodes - 1);
+
+    let mut written = String::new();
+    tree.write_dot(&mut written).unwrap();
+    assert_eq!(written, dot);
+
+    // The path to a value goes through one node per level down to it
+    let target = *tree.iter().nth(100).unwrap();
+    let mut depth = 0;
+    let mut subtree = tree.root();
+    while let Some(value) = subtree.root_value() {
+        depth += 1;
+        subtree = match target.cmp(value) {
+            core::cmp::Ordering::Less => subtree.left().unwrap(),
+            core::cmp::Ordering::Greater => subtree.right().unwrap(),
+            core::cmp::Ordering::Equal => break,
+        };
+    }
+    let highlighted = [target];
+    let dot = tree.to_dot_with(
+        &DotOptions::new()
+            .highlight_path(&target)
+            .highlight_values(&highlighted),
+    );
+    assert_eq!(dot.matches("color=red").count(), 2 * depth - 1);
+    assert_eq!(dot.matches("-> n").count(), edges);
+    assert_eq!(dot.matches(" [color=red").count(), depth - 1);
+    assert_eq!(dot.matches("fillcolor=lightblue").count(), 1);
+
+    let mut quoted = BST::new();
+    quoted.insert("say \"hi\"\\".to_string());
+    assert!(
+        quoted
+            .to_dot()
+            .contains(r#"[label="say \"hi\"\\\nh=1 bf=0"];"#)
+    );
+    assert_eq!(
+        BST::<i32>::new().to_dot(),
+        "digraph BST {\n    graph [ordering=out];\n}\n"
+    );
+}
+
+#[test]
+fn test_render() {
+    let tree: BST<_> = BST::from_sorted((1..=7).collect());
+    assert_eq!(tree.render().to_string(), " ┌─4─┐\n┌2┐ ┌6┐\n1 3 5 7");
+    assert_eq!(
+        tree.render().ascii().to_string(),
+        "  _4_\n /   \\\n 2   6\n/ \\ / \\\n1 3 5 7"
+    );
+
+    // Lone children hang on their own side
+    let mut lopsided = BST::new();
+    for i in [10, 5, 20, 1, 30] {
+        lopsided.insert(i);
+    }
+    assert_eq!(lopsided.render().to_string(), " ┌10┐\n┌5 20┐\n1   30");
+    assert_eq!(
+        lopsided.render().ascii().show_depth().to_string(),
+        "        10 (h=3)\n       /        \\\n    5 (h=2) 20 (h=2)\n   /                \\\n1 (h=1)         30 (h=1)"
+    );
+
+    // Wide trees lose their deepest levels, then get cut
+    let wide: BST<_> = BST::from_sorted((1..=100).collect());
+    let full = wide.render().to_string();
+    let narrow = wide.render().max_width(40).to_string();
+    assert!(full.lines().any(|line| line.chars().count() > 40));
+    assert!(narrow.lines().all(|line| line.chars().count() <= 40));
+    assert!(narrow.contains('…'));
+    let cut = wide.render().ascii().max_width(3).to_string();
+    assert!(cut.lines().all(|line| line.chars().count() <= 3));
+
+    assert_eq!(BST::<i32>::new().render().to_string(), "");
+}
+
+#[test]
+fn test_debug() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Config {
+        ports: BST<u16>,
+    }
+
+    let config = Config {
+        ports: BST::from_sorted(vec![80, 443]),
+    };
+    assert_eq!(std::format!("{config:?}"), "Config { ports: {80, 443} }");
+    assert_eq!(
+        std::format!("{config:#?}"),
+        "Config {\n    ports: {\n        80,\n        443,\n    },\n}"
+    );
+
+    let empty = BST::<i32>::new();
+    assert_eq!(std::format!("{empty:?}"), "{}");
+    assert_eq!(std::format!("{:?}", empty.tree_view()), "()");
+    assert_eq!(std::format!("{:#?}", empty.tree_view()), "()");
+
+    let mut tree = BST::new();
+    for i in [2, 1, 3, 4] {
+        tree.insert(i);
+    }
+    assert_eq!(std::format!("{:?}", tree.tree_view()), "((1) 2 (3 (4)))");
+    assert_eq!(
+        std::format!("{:#?}", tree.tree_view()),
+        "------4 [1]\n---3 [2]\n2 [3]\n---1 [1]"
+    );
+    assert_eq!(
+        std::format!("{:?}", tree.root()),
+        std::format!("{:?}", tree.tree_view())
+    );
+}
+
+#[test]
+fn test_svg_recording() {
+    use crate::{Rotation, svg::Frame};
+    use core::time::Duration;
+
+    let mut tree = BST::new();
+    let mut recording = tree.record();
+    // Each insertion needs at most one rotation, after which the tree is final
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        let before = recording.frames().len();
+        recording.insert(rng.gen_range(0..1000));
+        let frames = &recording.frames()[before..];
+        assert!(frames.is_empty() || frames.len() == 2);
+        if let [first, last] = frames {
+            assert!(first.before && !last.before);
+            assert_eq!(first.rotation, last.rotation);
+            assert_eq!(first.pivot, last.pivot);
+            assert_eq!(first.tree.count_nodes(), recording.count_nodes());
+            assert_eq!(&last.tree, recording.root());
+        }
+    }
+    let inserted = recording.frames().len();
+    for i in 0..1000 {
+        recording.remove(&i);
+    }
+    assert!(recording.is_empty());
+    let frames = recording.frames();
+    assert!(frames.len() > inserted);
+    assert!(frames.iter().all(|frame| frame.tree.count_nodes() > 0));
+
+    // A double rotation shows in one pair of frames
+    let mut tree = BST::new();
+    let mut recording = tree.record();
+    for i in [3, 1, 2] {
+        recording.insert(i);
+    }
+    let [before, after]: &[Frame<i32>; 2] = recording.frames().try_into().unwrap();
+    assert_eq!((before.rotation, before.pivot), (Rotation::LeftRight, 3));
+    assert_eq!(std::format!("{:?}", before.tree), "((1 (2)) 3)");
+    assert_eq!(std::format!("{:?}", after.tree), "((1) 2 (3))");
+    let svg = after.to_svg();
+    assert!(svg.contains(">after rotate_left_right at 3</text>"));
+    assert_eq!(svg.matches("<circle").count(), 3);
+
+    let animation = recording.to_animated_svg(Duration::from_millis(500));
+    assert!(animation.contains("dur=\"1s\""));
+    assert!(animation.contains("values=\"visible;hidden\" keyTimes=\"0;0.5\""));
+
+    #[cfg(feature = "std")]
+    {
+        let dir = std::env::temp_dir().join(std::format!("bst-svg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        recording.write_svg_frames(&dir).unwrap();
+        assert!(dir.join("frame-0002.svg").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    let mut quoted = BST::new();
+    quoted.insert("<a & b>");
+    assert!(quoted.to_svg().contains(">&lt;a &amp; b&gt;</text>"));
+}
+
+#[test]
+fn test_trace() {
+    use crate::{Rotation, Side, trace::TraceEvent};
+
+    let mut tree = BST::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..500 {
+        let val = rng.gen_range(0..300);
+        let mut plain = tree.clone();
+        let events = tree.insert_traced(val);
+        plain.insert(val);
+        assert_eq!(tree, plain);
+
+        // The path goes down through the ancestors of the value, in order
+        let path: std::vec::Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Descend { from, side } => Some((*from, *side)),
+                _ => None,
+            })
+            .collect();
+        for (from, side) in &path {
+            assert_eq!(*side == Side::Left, val < *from);
+        }
+        let rotations = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Rotate { .. }))
+            .count();
+        assert!(rotations <= 1);
+    }
+
+    let mut replaced = 0;
+    // Every value, out of order
+    for val in (0..300).map(|i| i * 7 % 300) {
+        let events = tree.remove_traced(&val);
+        for event in &events {
+            if let TraceEvent::Replace { removed, by } = event {
+                assert_eq!(*removed, val);
+                assert!(by < removed);
+                replaced += 1;
+            }
+        }
+    }
+    assert!(tree.is_empty());
+    assert!(replaced > 0);
+
+    // A double rotation is reported once, at the node that was unbalanced
+    let mut tree = BST::new();
+    tree.insert(3);
+    tree.insert(1);
+    assert!(tree.insert_traced(2).contains(&TraceEvent::Rotate {
+        rotation: Rotation::LeftRight,
+        pivot: 3
+    }));
+}
+
+#[test]
+fn test_stats() {
+    // The counters take no room without the feature, besides the tree and its clone function
+    #[cfg(not(feature = "stats"))]
+    assert_eq!(
+        core::mem::size_of::<BST<i32>>(),
+        core::mem::size_of::<Tree<i32>>() + core::mem::size_of::<fn(&i32) -> i32>()
+    );
+
+    #[cfg(feature = "stats")]
+    {
+        use crate::TreeStats;
+
+        let mut tree = BST::new();
+        for i in 1..=7 {
+            tree.insert(i);
+        }
+        assert_eq!(tree.stats().rotations, 4);
+        assert_eq!(tree.stats().allocations, 7);
+
+        // A search compares once per level down to the value
+        tree.reset_stats();
+        assert_eq!(tree.stats(), TreeStats::default());
+        assert!(tree.contains(&1));
+        assert_eq!(tree.stats().comparisons, 3);
+        // So do changes to a tree not sharing its nodes, present or not, a modified value then
+        // being compared to its predecessor to check it is still in order
+        tree.reset_stats();
+        tree.remove(&100);
+        assert!(!tree.modify(&0, |_| unreachable!()));
+        assert!(tree.modify(&7, |_| ()));
+        assert_eq!(tree.stats().comparisons, 3 + 3 + (3 + 1));
+
+        // Changing a clone copies the nodes it shares with the original on the path, below the
+        // root it copied already
+        let mut copy = tree.clone();
+        copy.reset_stats();
+        copy.insert(8);
+        assert_eq!(copy.stats().allocations, 1 + 2);
+        assert_eq!(tree.stats().allocations, 0);
+        drop(copy);
+
+        tree.reset_stats();
+        for i in 1..=7 {
+            tree.remove(&i);
+        }
+        let stats = tree.stats();
+        assert_eq!(stats.frees, 7);
+        assert_eq!(stats.allocations, 0);
+        assert!(stats.rotations > 0);
+
+        // Traced and recorded changes count just the same
+        let mut plain = BST::new();
+        let mut traced = BST::new();
+        let mut recorded = BST::new();
+        for i in 1..=7 {
+            plain.insert(i);
+            traced.insert_traced(i);
+            recorded.record().insert(i);
+        }
+        for i in [4, 1] {
+            plain.remove(&i);
+            traced.remove_traced(&i);
+            recorded.record().remove(&i);
+        }
+        assert_eq!(traced.stats(), plain.stats());
+        // The recording keeps a snapshot of the tree before each change, so the tree looks for
+        // the value before copying the nodes it then shares with it
+        let stats = recorded.stats();
+        assert_eq!(
+            (stats.rotations, stats.frees),
+            (plain.stats().rotations, plain.stats().frees)
+        );
+        assert!(stats.comparisons > plain.stats().comparisons);
+        assert!(stats.allocations > plain.stats().allocations);
+    }
+}
+
+#[test]
+fn test_shape_stats() {
+    let stats = BST::<i32>::new().shape_stats();
+    assert_eq!(
+        (stats.nodes, stats.height, stats.avg_path_length),
+        (0, 0, 0.0)
+    );
+    assert!(stats.leaf_depths.is_empty());
+
+    let mut tree = BST::new();
+    let mut rng = rand::thread_rng();
+    for _ in 0..5000 {
+        tree.insert(rng.gen_range(0..100_000));
+    }
+    let stats = tree.shape_stats();
+    assert_eq!(stats.nodes, tree.count_nodes());
+    assert_eq!(stats.height, stats.max_path_length);
+    assert!(stats.min_height <= stats.height && stats.height <= stats.max_avl_height);
+    assert!(stats.avg_path_length < stats.height as f64);
+    assert_eq!(stats.balance_factors.values().sum::<usize>(), stats.nodes);
+    assert!(stats.balance_factors.keys().all(|bf| bf.abs() <= 1));
+    assert_eq!(*stats.leaf_depths.keys().next_back().unwrap(), stats.height);
+
+    // The bounds of the smallest trees
+    let mut chain = BST::new();
+    for i in 0..12u32 {
+        chain.insert(i);
+        let stats = chain.shape_stats();
+        assert_eq!(stats.min_height, (i + 1).ilog2() as usize + 1);
+    }
+    assert_eq!(chain.shape_stats().max_avl_height, 5);
+}
+
+#[test]
+fn test_heap_size() {
+    use crate::HeapSize;
+    use std::{string::String, vec::Vec};
+
+    assert_eq!(BST::<u8>::new().heap_size_bytes(), 0);
+
+    // Each node allocates its two children
+    let mut tree = BST::new();
+    tree.insert(1u8);
+    let allocation = tree.heap_size_bytes() / 2;
+    assert!(allocation >= 2 * core::mem::size_of::<usize>() + core::mem::size_of::<Tree<u8>>());
+    for i in 2..=100 {
+        tree.insert(i);
+    }
+    assert_eq!(tree.heap_size_bytes(), 200 * allocation);
+    assert_eq!(tree.total_heap_size_bytes(), tree.heap_size_bytes());
+
+    // Values add what they own
+    let mut words = BST::new();
+    words.insert(String::with_capacity(10));
+    words.insert(String::from("abc"));
+    let nodes = words.heap_size_bytes();
+    assert_eq!(words.total_heap_size_bytes(), nodes + 10 + 3);
+
+    let nested: Vec<BST<String>> = Vec::from([words]);
+    assert_eq!(
+        nested.heap_size(),
+        core::mem::size_of::<BST<String>>() + nodes + 13
+    );
+}
+
+#[test]
+fn test_traversals() {
+    use std::vec::Vec;
+
+    let empty = BST::<i32>::new();
+    assert_eq!(empty.iter_level_order().next(), None);
+    assert_eq!(empty.iter_preorder().next(), None);
+    assert_eq!(empty.iter_postorder().next(), None);
+
+    let mut rng = rand::thread_rng();
+    let mut tree = BST::new();
+    for _ in 0..500 {
+        tree.insert(rng.gen_range(0..1000));
+    }
+
+    // Recursive references to compare against
+    fn preorder<'a>(tree: &'a Tree<i32>, out: &mut Vec<&'a i32>) {
+        if let Tree::Node {
+            left, value, right, ..
+        } = tree
+        {
+            out.push(value);
+            preorder(left, out);
+            preorder(right, out);
+        }
+    }
+    fn postorder<'a>(tree: &'a Tree<i32>, out: &mut Vec<&'a i32>) {
+        if let Tree::Node {
+            left, value, right, ..
+        } = tree
+        {
+            postorder(left, out);
+            postorder(right, out);
+            out.push(value);
+        }
+    }
+    let mut expected = Vec::new();
+    preorder(&tree.root, &mut expected);
+    assert_eq!(tree.iter_preorder().collect::<Vec<_>>(), expected);
+    expected.clear();
+    postorder(&tree.root, &mut expected);
+    assert_eq!(tree.iter_postorder().collect::<Vec<_>>(), expected);
+
+    // Levels only go up, each one sorted, and the last is the height
+    let levels: Vec<_> = tree.iter_level_order().with_levels().collect();
+    assert_eq!(levels.len(), tree.count_nodes());
+    assert_eq!(levels[0], (0, tree.root.root_value().unwrap()));
+    assert!(
+        levels
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1))
+    );
+    assert_eq!(levels.last().unwrap().0 + 1, tree.root.depth());
+    assert!(
+        tree.iter_level_order()
+            .eq(levels.iter().map(|&(_, value)| value))
+    );
+}
+
+#[test]
+fn test_walk() {
+    use crate::visit::{NodeInfo, Visitor, Walk};
+    use core::ops::ControlFlow;
+    use std::vec::Vec;
+
+    /// Records every callback, pruning below `prune` and stopping on `stop`.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<(char, Option<i32>, NodeInfo)>,
+        prune: Option<i32>,
+        stop: Option<i32>,
+    }
+
+    impl Visitor<i32> for Recorder {
+        fn enter_node(&mut self, value: &i32, info: NodeInfo) -> ControlFlow<Walk> {
+            self.events.push(('>', Some(*value), info));
+            if self.stop == Some(*value) {
+                ControlFlow::Break(Walk::Stop)
+            } else if self.prune == Some(*value) {
+                ControlFlow::Break(Walk::Prune)
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+
+        fn leave_node(&mut self, value: &i32, info: NodeInfo) -> ControlFlow<()> {
+            self.events.push(('<', Some(*value), info));
+            ControlFlow::Continue(())
+        }
+
+        fn visit_empty(&mut self, info: NodeInfo) -> ControlFlow<()> {
+            self.events.push(('.', None, info));
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut recorder = Recorder::default();
+    assert!(BST::<i32>::new().walk(&mut recorder).is_continue());
+    let root = NodeInfo {
+        depth: 0,
+        balance_factor: 0,
+        side: None,
+    };
+    assert_eq!(recorder.events, [('.', None, root)]);
+
+    let mut tree = BST::new();
+    for i in 0..200 {
+        tree.insert(i);
+    }
+
+    // Each node is entered and left once, around n + 1 empty subtrees
+    let mut recorder = Recorder::default();
+    assert!(tree.walk(&mut recorder).is_continue());
+    let count = |events: &[(char, Option<i32>, NodeInfo)], kind| {
+        events.iter().filter(|event| event.0 == kind).count()
+    };
+    assert_eq!(count(&recorder.events, '>'), 200);
+    assert_eq!(count(&recorder.events, '<'), 200);
+    assert_eq!(count(&recorder.events, '.'), 201);
+    let entered: Vec<_> = recorder
+        .events
+        .iter()
+        .filter(|event| event.0 == '>')
+        .map(|event| event.1.unwrap())
+        .collect();
+    assert!(entered.iter().eq(tree.iter_preorder()));
+    for (kind, _, info) in &recorder.events {
+        assert!(info.balance_factor.abs() <= 1);
+        assert_eq!(info.side.is_none(), info.depth == 0);
+        if *kind == '.' {
+            assert_eq!(info.balance_factor, 0);
+        }
+    }
+    let (_, _, first) = recorder.events[0];
+    assert_eq!(first.balance_factor, tree.root.balance_factor());
+
+    // Pruning the root skips everything below it
+    let root_value = *tree.root.root_value().unwrap();
+    let mut recorder = Recorder {
+        prune: Some(root_value),
+        ..Recorder::default()
+    };
+    assert!(tree.walk(&mut recorder).is_continue());
+    assert_eq!(recorder.events.len(), 2);
+    assert_eq!(recorder.events[1].0, '<');
+
+    // Stopping leaves nothing else
+    let stop = tree.iter_preorder().nth(5).copied();
+    let mut recorder = Recorder {
+        stop,
+        ..Recorder::default()
+    };
+    assert!(tree.walk(&mut recorder).is_break());
+    let (kind, value, _) = *recorder.events.last().unwrap();
+    assert_eq!((kind, value), ('>', stop));
+}
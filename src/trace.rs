@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt};
+
+use crate::{
+    BST, Tree,
+    compare::Compare,
+    tree::{Observer, Rotation, Side},
+};
+
+/// A step taken by an insertion or a removal, reported by [`BST::insert_traced`] and
+/// [`BST::remove_traced`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceEvent<T> {
+    /// The operation went down from the node holding `from` to its `side` child.
+    Descend {
+        /// The value of the node left.
+        from: T,
+        /// The child gone down to.
+        side: Side,
+    },
+    /// A subtree was rotated.
+    Rotate {
+        /// The rotation.
+        rotation: Rotation,
+        /// The value at the root of the subtree, before the rotation.
+        pivot: T,
+    },
+    /// The cached depth of a node changed.
+    DepthUpdate {
+        /// The value of the node.
+        value: T,
+        /// The depth before the change.
+        old: usize,
+        /// The depth after the change.
+        new: usize,
+    },
+    /// A removed value, whose node had two children, was replaced by the largest value of its
+    /// left subtree.
+    Replace {
+        /// The removed value.
+        removed: T,
+        /// The value taking its place.
+        by: T,
+    },
+}
+
+impl<T> fmt::Display for TraceEvent<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Descend {
+                from,
+                side: Side::Left,
+            } => write!(f, "left of {from}"),
+            Self::Descend {
+                from,
+                side: Side::Right,
+            } => write!(f, "right of {from}"),
+            Self::Rotate { rotation, pivot } => write!(f, "{rotation} at {pivot}"),
+            Self::DepthUpdate { value, old, new } => write!(f, "depth of {value}: {old} -> {new}"),
+            Self::Replace { removed, by } => write!(f, "{removed} replaced by {by}"),
+        }
+    }
+}
+
+/// Helper struct collecting the steps of an operation as events.
+struct Tracer<T> {
+    events: Vec<TraceEvent<T>>,
+}
+
+impl<T> Observer<T> for Tracer<T>
+where
+    T: Clone,
+{
+    fn descend(&mut self, value: &T, side: Side) {
+        self.events.push(TraceEvent::Descend {
+            from: value.clone(),
+            side,
+        });
+    }
+
+    fn before_rotation(&mut self, rotation: Rotation, tree: &Tree<T>) {
+        if let Some(pivot) = tree.root_value() {
+            self.events.push(TraceEvent::Rotate {
+                rotation,
+                pivot: pivot.clone(),
+            });
+        }
+    }
+
+    fn depth_updated(&mut self, value: &T, old: usize, new: usize) {
+        self.events.push(TraceEvent::DepthUpdate {
+            value: value.clone(),
+            old,
+            new,
+        });
+    }
+
+    fn replaced(&mut self, removed: &T, by: &T) {
+        self.events.push(TraceEvent::Replace {
+            removed: removed.clone(),
+            by: by.clone(),
+        });
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Same as [`BST::insert`], but returns the steps it took, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, Rotation, Side, trace::TraceEvent};
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// let events = tree.insert_traced(3);
+    /// assert_eq!(
+    ///     events,
+    ///     [
+    ///         TraceEvent::Descend { from: 1, side: Side::Right },
+    ///         TraceEvent::Descend { from: 2, side: Side::Right },
+    ///         TraceEvent::DepthUpdate { value: 2, old: 1, new: 2 },
+    ///         TraceEvent::DepthUpdate { value: 1, old: 2, new: 3 },
+    ///         TraceEvent::Rotate { rotation: Rotation::Left, pivot: 1 },
+    ///     ]
+    /// );
+    /// assert_eq!(events[4].to_string(), "rotate_left at 1");
+    /// ```
+    pub fn insert_traced(&mut self, val: T) -> Vec<TraceEvent<T>>
+    where
+        T: Clone,
+        C: Compare<T>,
+    {
+        let mut obs = (&self.stats, Tracer { events: Vec::new() });
+        self.root.insert_tracked(
+            val,
+            &self.stats.counted(&self.cmp),
+            None,
+            self.cloner.get(),
+            &mut obs,
+        );
+        self.debug_check_invariants();
+        obs.1.events
+    }
+
+    /// Same as [`BST::remove`], but returns the steps it took, in order. Nothing is returned
+    /// if the value isn't in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, trace::TraceEvent};
+    ///
+    /// let mut tree: BST<_> = BST::from_sorted(vec![1, 2, 3]);
+    /// let events = tree.remove_traced(&2);
+    /// assert!(events.contains(&TraceEvent::Replace { removed: 2, by: 1 }));
+    /// assert!(tree.remove_traced(&2).is_empty());
+    /// ```
+    pub fn remove_traced<Q>(&mut self, val: &Q) -> Vec<TraceEvent<T>>
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+    {
+        let mut obs = (&self.stats, Tracer { events: Vec::new() });
+        let removed = self.root.remove(
+            val,
+            &self.stats.counted(&self.cmp),
+            self.cloner.get(),
+            &mut obs,
+        );
+        self.debug_check_invariants();
+        if !removed {
+            // The way down to where the value would be isn't a step of its removal
+            obs.1.events.clear();
+        }
+        obs.1.events
+    }
+}
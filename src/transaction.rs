@@ -0,0 +1,79 @@
+use core::{borrow::Borrow, ops::Deref};
+
+use crate::{BST, Tree, compare::Compare, shared::DefaultKind, tree::Augment};
+
+/// A batch of changes to a [`BST`], rolled back if it fails.
+///
+/// Created by [`BST::transaction`]. It dereferences to the tree, so every read sees the
+/// changes made so far.
+pub struct Transaction<'a, T, C, A = ()> {
+    tree: &'a mut BST<T, C, A>,
+    /// The tree to roll back to when dropped, unless the batch was committed.
+    snapshot: Option<Tree<T, DefaultKind, A>>,
+}
+
+impl<'a, T, C, A: Augment<T>> Transaction<'a, T, C, A> {
+    pub(crate) fn new(tree: &'a mut BST<T, C, A>) -> Self
+    where
+        T: Clone,
+    {
+        tree.cloner.record();
+        let snapshot = Some(tree.root.shallow_copy(T::clone));
+        Self { tree, snapshot }
+    }
+
+    /// Keeps the changes made so far, rather than rolling them back when dropped.
+    pub(crate) fn commit(mut self) {
+        self.snapshot = None;
+    }
+
+    /// Inserts a value into the tree, as [`BST::insert`] does.
+    pub fn insert(&mut self, val: T)
+    where
+        C: Compare<T>,
+    {
+        self.tree.insert(val);
+    }
+
+    /// Removes a value from the tree, as [`BST::remove`] does.
+    pub fn remove<Q>(&mut self, val: &Q)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+    {
+        self.tree.remove(val);
+    }
+
+    /// Modifies a value of the tree in place, as [`BST::modify`] does.
+    pub fn modify<Q, F>(&mut self, val: &Q, f: F) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+        F: FnOnce(&mut T),
+    {
+        self.tree.modify(val, f)
+    }
+
+    /// Removes all the values of the tree.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
+
+impl<T, C, A> Drop for Transaction<'_, T, C, A> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.tree.root = snapshot;
+        }
+    }
+}
+
+impl<T, C, A> Deref for Transaction<'_, T, C, A> {
+    type Target = BST<T, C, A>;
+
+    fn deref(&self) -> &Self::Target {
+        self.tree
+    }
+}
@@ -0,0 +1,1169 @@
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    cmp::{Ordering, max},
+    fmt,
+};
+
+use crate::{
+    InvariantError,
+    compare::Compare,
+    render::TreeView,
+    shared::{CloneFn, DefaultKind, SharedKind, Subtree},
+};
+
+/// The nodes of a [`BST`](crate::BST), each one the root of a subtree.
+#[derive(Default)]
+pub enum Tree<T, K: SharedKind = DefaultKind, A = ()> {
+    /// Represents an empty tree.
+    #[default]
+    Empty,
+    /// Represents a BST node containing a value and optional left and right subtrees.
+    Node {
+        left: Subtree<T, K, A>,
+        value: T,
+        right: Subtree<T, K, A>,
+        depth: usize,
+        /// What the node caches about its subtree besides its depth.
+        aug: A,
+    },
+}
+
+/// What a node caches about its subtree besides its depth, recomputed whenever the node or
+/// its children change. `()` caches nothing, so that the trees which don't need it don't pay
+/// for it.
+pub trait Augment<T>: Clone + PartialEq {
+    /// Computes what the node holding `value` caches, out of what its children do.
+    fn compute(value: &T, left: Option<&Self>, right: Option<&Self>) -> Self;
+}
+
+impl<T> Augment<T> for () {
+    fn compute(_value: &T, _left: Option<&()>, _right: Option<&()>) {}
+}
+
+/// Which child of a node a value lives under.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    /// The left child, holding the smaller values.
+    Left,
+    /// The right child, holding the larger values.
+    Right,
+}
+
+impl Side {
+    fn flip(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+/// The rotations rebalancing a subtree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rotation {
+    /// The right child moves up to the root.
+    Left,
+    /// The left child moves up to the root.
+    Right,
+    /// The left subtree is rotated left, then the whole subtree right.
+    LeftRight,
+    /// The right subtree is rotated right, then the whole subtree left.
+    RightLeft,
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Left => "rotate_left",
+            Self::Right => "rotate_right",
+            Self::LeftRight => "rotate_left_right",
+            Self::RightLeft => "rotate_right_left",
+        })
+    }
+}
+
+/// Hooks following an operation through the tree as it changes it. They all do nothing by
+/// default, and `()` observes nothing, so that unobserved operations don't pay for them.
+pub(crate) trait Observer<T, K: SharedKind = DefaultKind, A = ()> {
+    /// Called when the operation goes down to the `side` child of the current node, which
+    /// holds `value`.
+    fn descend(&mut self, _value: &T, _side: Side) {}
+
+    /// Called when the operation goes back up from a child to its parent.
+    fn ascend(&mut self) {}
+
+    /// Called right before the current subtree is rotated.
+    fn before_rotation(&mut self, _rotation: Rotation, _tree: &Tree<T, K, A>) {}
+
+    /// Called right after the current subtree was rotated.
+    fn after_rotation(&mut self, _rotation: Rotation, _tree: &Tree<T, K, A>) {}
+
+    /// Called when the cached depth of the node holding `value` changes.
+    fn depth_updated(&mut self, _value: &T, _old: usize, _new: usize) {}
+
+    /// Called when a removed value with two children is replaced by the largest value of its
+    /// left subtree.
+    fn replaced(&mut self, _removed: &T, _by: &T) {}
+
+    /// Called when a node is allocated, for a new value or to copy a node shared with another
+    /// tree.
+    fn allocated(&mut self) {}
+
+    /// Called when the node of a removed value is freed.
+    fn freed(&mut self) {}
+}
+
+impl<T, K: SharedKind, A> Observer<T, K, A> for () {}
+
+/// Observes with both observers, the first one first.
+impl<T, K, A, O1, O2> Observer<T, K, A> for (O1, O2)
+where
+    K: SharedKind,
+    O1: Observer<T, K, A>,
+    O2: Observer<T, K, A>,
+{
+    fn descend(&mut self, value: &T, side: Side) {
+        self.0.descend(value, side);
+        self.1.descend(value, side);
+    }
+
+    fn ascend(&mut self) {
+        self.0.ascend();
+        self.1.ascend();
+    }
+
+    fn before_rotation(&mut self, rotation: Rotation, tree: &Tree<T, K, A>) {
+        self.0.before_rotation(rotation, tree);
+        self.1.before_rotation(rotation, tree);
+    }
+
+    fn after_rotation(&mut self, rotation: Rotation, tree: &Tree<T, K, A>) {
+        self.0.after_rotation(rotation, tree);
+        self.1.after_rotation(rotation, tree);
+    }
+
+    fn depth_updated(&mut self, value: &T, old: usize, new: usize) {
+        self.0.depth_updated(value, old, new);
+        self.1.depth_updated(value, old, new);
+    }
+
+    fn replaced(&mut self, removed: &T, by: &T) {
+        self.0.replaced(removed, by);
+        self.1.replaced(removed, by);
+    }
+
+    fn allocated(&mut self) {
+        self.0.allocated();
+        self.1.allocated();
+    }
+
+    fn freed(&mut self) {
+        self.0.freed();
+        self.1.freed();
+    }
+}
+
+/// Rewrites a path (stored bottom-up, so the first step is the last element)
+/// to follow the same value after the node it starts from has been rotated
+/// towards `towards`.
+///
+/// A right rotation is `towards == Side::Right`.
+fn rotate_path(path: &mut Vec<Side>, towards: Side) {
+    let away = towards.flip();
+    match path.pop() {
+        // The old root moves down to become the `towards` child.
+        None => path.push(towards),
+        Some(side) if side == towards => {
+            path.push(towards);
+            path.push(towards);
+        }
+        Some(_) => match path.pop() {
+            // The `away` child becomes the new root.
+            None => {}
+            Some(side) if side == away => path.push(away),
+            Some(_) => {
+                path.push(away);
+                path.push(towards);
+            }
+        },
+    }
+}
+
+/// Same as [`rotate_path`], for a rotation of the `child` subtree of the node the path starts
+/// from.
+fn rotate_child_path(path: &mut Vec<Side>, child: Side, towards: Side) {
+    if path.last() == Some(&child) {
+        path.pop();
+        rotate_path(path, towards);
+        path.push(child);
+    }
+}
+
+impl<T, K: SharedKind, A> Tree<T, K, A> {
+    /// Checks if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+
+    /// Returns a reference to the value of the root node, if the tree is not empty.
+    pub fn root_value(&self) -> Option<&T> {
+        match self {
+            Self::Empty => None,
+            Self::Node { value, .. } => Some(value),
+        }
+    }
+
+    /// Counts the number of nodes in the tree.
+    pub fn count_nodes(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { left, right, .. } => {
+                let left_count = left.count_nodes();
+                let right_count = right.count_nodes();
+                1 + left_count + right_count
+            }
+        }
+    }
+
+    /// Returns what the root node caches about the tree, if the tree is not empty.
+    pub(crate) fn aug(&self) -> Option<&A> {
+        match self {
+            Self::Empty => None,
+            Self::Node { aug, .. } => Some(aug),
+        }
+    }
+
+    /// Computes the depth/height of the tree, including the root node.
+    pub fn depth(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { depth, .. } => *depth,
+        }
+    }
+
+    // /// Inserts a value into the tree without balancing.
+    // ///
+    // /// If the value already exists, it will not be inserted again.
+    // fn insert_unbalanced(&mut self, val: T) -> &mut Self
+    // where
+    //     T: PartialEq + Ord,
+    // {
+    //     match self {
+    //         Self::Empty => {
+    //             *self = Self::Node {
+    //                 left: Box::new(Self::Empty),
+    //                 value: val,
+    //                 right: Box::new(Self::Empty),
+    //             };
+    //             return self;
+    //         }
+    //         Self::Node { left, value, right } => {
+    //             if *value == val {
+    //                 return self;
+    //             }
+    //             if val < *value {
+    //                 left.insert_unbalanced(val);
+    //                 return self;
+    //             } else {
+    //                 right.insert_unbalanced(val);
+    //                 return self;
+    //             }
+    //         }
+    //     }
+    // }
+
+    /// Returns a reference to the left subtree of the root node, if it isn't empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// assert_eq!(tree.root().left().and_then(|t| t.root_value()), Some(&1));
+    /// assert!(tree.root().right().is_none());
+    /// ```
+    pub fn left(&self) -> Option<&Self> {
+        match self {
+            Self::Node { left, .. } if !left.is_empty() => Some(left),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the right subtree of the root node, if it isn't empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::BST;
+    ///
+    /// let mut tree = BST::new();
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// assert_eq!(tree.root().right().and_then(|t| t.root_value()), Some(&2));
+    /// assert!(tree.root().left().is_none());
+    /// ```
+    pub fn right(&self) -> Option<&Self> {
+        match self {
+            Self::Node { right, .. } if !right.is_empty() => Some(right),
+            _ => None,
+        }
+    }
+
+    /// Helper function to get a reference to the node at the left end of the tree.
+    #[cfg(test)]
+    pub(crate) fn left_end(&self) -> Option<&Self> {
+        match self {
+            Tree::Node { left, .. } if !left.is_empty() => left.left_end(),
+            Tree::Node { .. } => Some(self),
+            Tree::Empty => None,
+        }
+    }
+
+    /// Helper function to get a reference to the node at the right end of the tree.
+    #[cfg(test)]
+    pub(crate) fn right_end(&self) -> Option<&Self> {
+        match self {
+            Tree::Node { right, .. } if !right.is_empty() => right.right_end(),
+            Tree::Node { .. } => Some(self),
+            Tree::Empty => None,
+        }
+    }
+
+    /// Helper function to get the balance factor of the tree.
+    ///
+    /// This is effectively `depth(left) - depth(right)`.
+    pub fn balance_factor(&self) -> isize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { left, right, .. } => {
+                let left_depth = left.depth();
+                let right_depth = right.depth();
+                left_depth as isize - right_depth as isize
+            }
+        }
+    }
+
+    /// Searches for the subtree rooted at a value.
+    pub(crate) fn find<Q, C>(&self, val: &Q, cmp: &C) -> Option<&Self>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        match self {
+            Self::Empty => None,
+            Self::Node {
+                left, value, right, ..
+            } => match cmp.compare(val, value.borrow()) {
+                Ordering::Equal => Some(self),
+                Ordering::Less => left.find(val, cmp),
+                Ordering::Greater => right.find(val, cmp),
+            },
+        }
+    }
+
+    /// Helper function to look for a value without changing anything, recording in `path` the
+    /// way down to it as [`Tree::insert_tracked`] does.
+    fn find_tracked<C>(&self, val: &T, cmp: &C, path: Option<&mut Vec<Side>>) -> bool
+    where
+        C: Compare<T>,
+    {
+        let Self::Node {
+            left, value, right, ..
+        } = self
+        else {
+            return false;
+        };
+        let (side, child) = match cmp.compare(val, value) {
+            Ordering::Equal => return true,
+            Ordering::Less => (Side::Left, left),
+            Ordering::Greater => (Side::Right, right),
+        };
+        let mut path = path;
+        let found = child.find_tracked(val, cmp, path.as_deref_mut());
+        if found && let Some(path) = path {
+            path.push(side);
+        }
+        found
+    }
+
+    /// Helper function to get the value at the end of a path recorded by
+    /// [`Tree::insert_tracked`].
+    pub(crate) fn value_at(&self, mut path: Vec<Side>) -> Option<&T> {
+        let mut node = self;
+        while let Some(side) = path.pop() {
+            node = match (node, side) {
+                (Self::Node { left, .. }, Side::Left) => left,
+                (Self::Node { right, .. }, Side::Right) => right,
+                (Self::Empty, _) => return None,
+            };
+        }
+        node.root_value()
+    }
+
+    /// Helper function to get the smallest value in the tree.
+    fn min_value(&self) -> Option<&T> {
+        match self {
+            Self::Node { left, .. } if !left.is_empty() => left.min_value(),
+            _ => self.root_value(),
+        }
+    }
+
+    /// Helper function to get the largest value in the tree.
+    fn max_value(&self) -> Option<&T> {
+        match self {
+            Self::Node { right, .. } if !right.is_empty() => right.max_value(),
+            _ => self.root_value(),
+        }
+    }
+}
+
+/// Operations maintaining what the nodes cache about their subtrees.
+impl<T, K: SharedKind, A: Augment<T>> Tree<T, K, A> {
+    /// Checks the tree invariants with every value between `lower` and `upper`, returning the
+    /// actual depth of the tree.
+    pub(crate) fn check_invariants<C>(
+        &self,
+        cmp: &C,
+        lower: Option<&T>,
+        upper: Option<&T>,
+    ) -> Result<usize, InvariantError>
+    where
+        C: Compare<T>,
+    {
+        match self {
+            Self::Empty => Ok(0),
+            Self::Node {
+                left,
+                value,
+                right,
+                depth,
+                aug,
+            } => {
+                let lt = |a: &T, b: &T| cmp.compare(a, b) == Ordering::Less;
+                if lower.is_some_and(|lower| !lt(lower, value))
+                    || upper.is_some_and(|upper| !lt(value, upper))
+                {
+                    return Err(InvariantError::Unordered);
+                }
+                let left_depth = left.check_invariants(cmp, lower, Some(value))?;
+                let right_depth = right.check_invariants(cmp, Some(value), upper)?;
+                if left_depth.abs_diff(right_depth) > 1 {
+                    return Err(InvariantError::Unbalanced);
+                }
+                if *depth != 1 + max(left_depth, right_depth) {
+                    return Err(InvariantError::WrongDepth);
+                }
+                if *aug != A::compute(value, left.aug(), right.aug()) {
+                    return Err(InvariantError::WrongSize);
+                }
+                Ok(*depth)
+            }
+        }
+    }
+
+    /// Helper to update the height of the current node, and what it caches, based on its
+    /// children.
+    fn update_depth(&mut self) {
+        if let Self::Node {
+            left,
+            value,
+            right,
+            depth,
+            aug,
+        } = self
+        {
+            *depth = 1 + max(left.depth(), right.depth());
+            *aug = A::compute(value, left.aug(), right.aug());
+        }
+    }
+
+    /// Helper to build a node out of its subtrees and value, which must be in order.
+    pub(crate) fn node(left: Self, value: T, right: Self) -> Self {
+        Self::join_node(Subtree::new(left), value, Subtree::new(right))
+    }
+
+    /// Same as [`Tree::node`], with the subtrees already behind their pointers.
+    fn join_node(left: Subtree<T, K, A>, value: T, right: Subtree<T, K, A>) -> Self {
+        Self::Node {
+            depth: 1 + max(left.depth(), right.depth()),
+            aug: A::compute(&value, left.aug(), right.aug()),
+            left,
+            value,
+            right,
+        }
+    }
+
+    /// Helper to copy the root node, cloning its value with `clone` and sharing its subtrees.
+    pub(crate) fn shallow_copy(&self, clone: CloneFn<T>) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Node {
+                left,
+                value,
+                right,
+                depth,
+                aug,
+            } => Self::Node {
+                left: left.share(),
+                value: clone(value),
+                right: right.share(),
+                depth: *depth,
+                aug: aug.clone(),
+            },
+        }
+    }
+
+    /// Builds a balanced tree out of the next `len` values of `values`, which must be sorted.
+    pub(crate) fn from_sorted(len: usize, values: &mut impl Iterator<Item = T>) -> Self {
+        if len == 0 {
+            return Self::Empty;
+        }
+        let left = Self::from_sorted(len / 2, values);
+        let value = values.next().expect("iterator should yield `len` values");
+        let right = Self::from_sorted(len - len / 2 - 1, values);
+        Self::node(left, value, right)
+    }
+}
+
+/// Operations changing the tree, which copy the nodes they change out of any other tree
+/// sharing them, cloning their values with `clone`.
+impl<T, K: SharedKind, A: Augment<T>> Tree<T, K, A> {
+    /// Helper function to rotate the tree left.
+    fn rotate_left<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) {
+        let (value, left, right) = match core::mem::take(self) {
+            Self::Node {
+                value, left, right, ..
+            } => (value, left, right),
+            other => {
+                *self = other;
+                return;
+            }
+        };
+
+        match right.into_tree(clone, obs) {
+            Self::Node {
+                value: r_value,
+                left: r_left,
+                right: r_right,
+                ..
+            } => {
+                let new_left = Self::join_node(left, value, r_left);
+                *self = Self::join_node(Subtree::new(new_left), r_value, r_right);
+            }
+            Self::Empty => {
+                // Cannot rotate, put back
+                *self = Self::join_node(left, value, Subtree::new(Self::Empty));
+            }
+        }
+    }
+
+    /// Helper function to rotate the tree right.
+    fn rotate_right<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) {
+        let (value, right, left) = match core::mem::take(self) {
+            Self::Node {
+                value, left, right, ..
+            } => (value, right, left),
+            other => {
+                *self = other;
+                return;
+            }
+        };
+
+        match left.into_tree(clone, obs) {
+            Self::Node {
+                value: l_value,
+                left: l_left,
+                right: l_right,
+                ..
+            } => {
+                let new_right = Self::join_node(l_right, value, right);
+                *self = Self::join_node(l_left, l_value, Subtree::new(new_right));
+            }
+            Self::Empty => {
+                // Cannot rotate, put back
+                *self = Self::join_node(Subtree::new(Self::Empty), value, right);
+            }
+        }
+    }
+
+    /// Helper function to rotate the left subtree left, and then the whole tree right.
+    fn rotate_left_right<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) {
+        match self {
+            Self::Empty => (),
+            Self::Node { left, .. } => {
+                if !left.is_empty() {
+                    left.make_mut(clone, obs).rotate_left(clone, obs);
+                    self.rotate_right(clone, obs);
+                }
+            }
+        }
+    }
+
+    /// Helper function to rotate the left subtree right, and then the whole tree left.
+    fn rotate_right_left<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) {
+        match self {
+            Self::Empty => (),
+            Self::Node { right, .. } => {
+                if !right.is_empty() {
+                    right.make_mut(clone, obs).rotate_right(clone, obs);
+                    self.rotate_left(clone, obs);
+                }
+            }
+        }
+    }
+
+    /// Same as [`Tree::update_depth`], telling `obs` if the depth changes.
+    fn update_depth_observed<O: Observer<T, K, A>>(&mut self, obs: &mut O) {
+        let old = self.depth();
+        self.update_depth();
+        if let Self::Node { value, depth, .. } = self
+            && *depth != old
+        {
+            obs.depth_updated(value, old, *depth);
+        }
+    }
+
+    /// Rebalances the whole tree after it has a `|balance_factor|` of 1 or more.
+    // FIX: slower than it needs to be: height/depth should be stored or something
+    fn rebalance(&mut self, clone: CloneFn<T>) {
+        self.rebalance_tracked(None, clone, &mut ());
+    }
+
+    /// Same as [`Tree::rebalance`], but keeps `path` pointing at the same value through the
+    /// rotations, and tells `obs` about them.
+    fn rebalance_tracked<O>(
+        &mut self,
+        mut path: Option<&mut Vec<Side>>,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) where
+        O: Observer<T, K, A>,
+    {
+        loop {
+            let bf = self.balance_factor();
+
+            match self {
+                Self::Empty => return,
+                Self::Node { left, right, .. } => {
+                    if bf > 1 {
+                        if !left.is_empty() {
+                            if left.balance_factor() >= 0 {
+                                obs.before_rotation(Rotation::Right, self);
+                                self.rotate_right(clone, obs);
+                                obs.after_rotation(Rotation::Right, self);
+                            } else {
+                                obs.before_rotation(Rotation::LeftRight, self);
+                                self.rotate_left_right(clone, obs);
+                                obs.after_rotation(Rotation::LeftRight, self);
+                                if let Some(path) = path.as_deref_mut() {
+                                    rotate_child_path(path, Side::Left, Side::Left);
+                                }
+                            }
+                            if let Some(path) = path.as_deref_mut() {
+                                rotate_path(path, Side::Right);
+                            }
+                        }
+                    } else if bf < -1 {
+                        if !right.is_empty() {
+                            if right.balance_factor() <= 0 {
+                                obs.before_rotation(Rotation::Left, self);
+                                self.rotate_left(clone, obs);
+                                obs.after_rotation(Rotation::Left, self);
+                            } else {
+                                obs.before_rotation(Rotation::RightLeft, self);
+                                self.rotate_right_left(clone, obs);
+                                obs.after_rotation(Rotation::RightLeft, self);
+                                if let Some(path) = path.as_deref_mut() {
+                                    rotate_child_path(path, Side::Right, Side::Right);
+                                }
+                            }
+                            if let Some(path) = path.as_deref_mut() {
+                                rotate_path(path, Side::Left);
+                            }
+                        }
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Inserts a value, rebalancing the tree right away, and records in `path` the way from
+    /// this node down to the inserted value, or to the equal value that was already there.
+    pub(crate) fn insert_tracked<C, O>(
+        &mut self,
+        val: T,
+        cmp: &C,
+        path: Option<&mut Vec<Side>>,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) where
+        C: Compare<T>,
+        O: Observer<T, K, A>,
+    {
+        self.insert_missing(val, cmp, path, false, clone, obs);
+    }
+
+    /// Same as [`Tree::insert_tracked`], knowing whether the value is `missing` from the tree.
+    ///
+    /// Shared subtrees are only copied on the way down once the value is known to be missing,
+    /// which takes a search the first time one is met.
+    pub(crate) fn insert_missing<C, O>(
+        &mut self,
+        val: T,
+        cmp: &C,
+        mut path: Option<&mut Vec<Side>>,
+        mut missing: bool,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) where
+        C: Compare<T>,
+        O: Observer<T, K, A>,
+    {
+        match self {
+            Self::Empty => {
+                obs.allocated();
+                *self = Self::node(Self::Empty, val, Self::Empty);
+            }
+            Self::Node {
+                left, value, right, ..
+            } => {
+                let (side, child) = match cmp.compare(&val, value) {
+                    Ordering::Equal => return,
+                    Ordering::Less => (Side::Left, left),
+                    Ordering::Greater => (Side::Right, right),
+                };
+                if !missing && child.is_shared() {
+                    if child.find_tracked(&val, cmp, path.as_deref_mut()) {
+                        if let Some(path) = path {
+                            path.push(side);
+                        }
+                        return;
+                    }
+                    missing = true;
+                }
+                obs.descend(value, side);
+                child.make_mut(clone, obs).insert_missing(
+                    val,
+                    cmp,
+                    path.as_deref_mut(),
+                    missing,
+                    clone,
+                    obs,
+                );
+                obs.ascend();
+                if let Some(path) = path.as_deref_mut() {
+                    path.push(side);
+                }
+                self.update_depth_observed(obs);
+                self.rebalance_tracked(path, clone, obs);
+            }
+        }
+    }
+
+    /// Helper function to take ownership of the largest value in the tree.
+    fn take_max<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) -> Option<T> {
+        match self {
+            Self::Empty => None,
+            Self::Node { value, right, .. } if !right.is_empty() => {
+                obs.descend(value, Side::Right);
+                let val = right.make_mut(clone, obs).take_max(clone, obs);
+                obs.ascend();
+                self.update_depth_observed(obs);
+                self.rebalance_tracked(None, clone, obs);
+                val
+            }
+            // This node is the max
+            Self::Node { .. } => {
+                let old_self = core::mem::take(self);
+                if let Self::Node { value, left, .. } = old_self {
+                    // Replace this node with its left child
+                    obs.freed();
+                    *self = left.into_tree(clone, obs);
+                    Some(value)
+                } else {
+                    unreachable!()
+                }
+            }
+        }
+    }
+
+    /// Helper function to take ownership of the value at the root of the tree, replacing the
+    /// node with what is left of its subtrees.
+    fn take_root<O: Observer<T, K, A>>(&mut self, clone: CloneFn<T>, obs: &mut O) -> Option<T> {
+        match self {
+            Self::Empty => None,
+            Self::Node { left, right, .. } if left.is_empty() || right.is_empty() => {
+                let Self::Node {
+                    left, value, right, ..
+                } = core::mem::take(self)
+                else {
+                    unreachable!()
+                };
+                obs.freed();
+                *self = if left.is_empty() { right } else { left }.into_tree(clone, obs);
+                Some(value)
+            }
+            // Two children
+            Self::Node { left, value, .. } => {
+                obs.descend(value, Side::Left);
+                let max = left.make_mut(clone, obs).take_max(clone, obs);
+                obs.ascend();
+                let max = max?;
+                obs.replaced(value, &max);
+                let old = core::mem::replace(value, max);
+                self.update_depth_observed(obs);
+                self.rebalance_tracked(None, clone, obs);
+                Some(old)
+            }
+        }
+    }
+
+    /// Helper function to get `child` ready to change the value equal to `val` in it, copying
+    /// it first if another tree shares it.
+    ///
+    /// A shared subtree is searched first, the first time one is met, so that it isn't copied
+    /// for nothing: [`None`] is returned if the value isn't there, and `present` records that
+    /// it is otherwise.
+    fn make_mut_present<'a, Q, C, O>(
+        child: &'a mut Subtree<T, K, A>,
+        val: &Q,
+        cmp: &C,
+        present: &mut bool,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) -> Option<&'a mut Self>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        O: Observer<T, K, A>,
+    {
+        if !*present && child.is_shared() {
+            child.find(val, cmp)?;
+            *present = true;
+        }
+        Some(child.make_mut(clone, obs))
+    }
+
+    /// Removes a value from the tree, returning whether it was there.
+    pub(crate) fn remove<Q, C, O>(
+        &mut self,
+        val: &Q,
+        cmp: &C,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        O: Observer<T, K, A>,
+    {
+        self.remove_present(val, cmp, false, clone, obs)
+    }
+
+    /// Same as [`Tree::remove`], knowing whether the value is `present` in the tree.
+    fn remove_present<Q, C, O>(
+        &mut self,
+        val: &Q,
+        cmp: &C,
+        mut present: bool,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        O: Observer<T, K, A>,
+    {
+        let removed = match self {
+            Self::Empty => false,
+            Self::Node {
+                left, value, right, ..
+            } => match cmp.compare(val, (*value).borrow()) {
+                Ordering::Less => {
+                    let Some(left) =
+                        Self::make_mut_present(left, val, cmp, &mut present, clone, obs)
+                    else {
+                        return false;
+                    };
+                    obs.descend(value, Side::Left);
+                    let removed = left.remove_present(val, cmp, present, clone, obs);
+                    obs.ascend();
+                    removed
+                }
+                Ordering::Greater => {
+                    let Some(right) =
+                        Self::make_mut_present(right, val, cmp, &mut present, clone, obs)
+                    else {
+                        return false;
+                    };
+                    obs.descend(value, Side::Right);
+                    let removed = right.remove_present(val, cmp, present, clone, obs);
+                    obs.ascend();
+                    removed
+                }
+                // Node found
+                Ordering::Equal => {
+                    self.take_root(clone, obs);
+                    true
+                }
+            },
+        };
+
+        if removed {
+            self.update_depth_observed(obs);
+            self.rebalance_tracked(None, clone, obs);
+        }
+        removed
+    }
+
+    /// Modifies the value equal to `val` in place.
+    ///
+    /// Returns [`None`] if the value wasn't found, and the value itself if it had to be taken
+    /// out of the tree.
+    pub(crate) fn modify<Q, C, F, O>(
+        &mut self,
+        val: &Q,
+        cmp: &C,
+        f: F,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) -> Option<Option<T>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+        F: FnOnce(&mut T),
+        O: Observer<T, K, A>,
+    {
+        self.modify_present(val, cmp, f, (None, None), false, clone, obs)
+    }
+
+    /// Same as [`Tree::modify`], with `lower` and `upper` the closest values the ancestors
+    /// require the modified value to sit between, and knowing whether the value is `present`
+    /// in the tree.
+    #[allow(clippy::too_many_arguments)]
+    fn modify_present<Q, C, F, O>(
+        &mut self,
+        val: &Q,
+        cmp: &C,
+        f: F,
+        (lower, upper): (Option<&T>, Option<&T>),
+        mut present: bool,
+        clone: CloneFn<T>,
+        obs: &mut O,
+    ) -> Option<Option<T>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+        F: FnOnce(&mut T),
+        O: Observer<T, K, A>,
+    {
+        let res = match self {
+            Self::Empty => return None,
+            Self::Node {
+                left, value, right, ..
+            } => match cmp.compare(val, (*value).borrow()) {
+                Ordering::Less => Self::make_mut_present(left, val, cmp, &mut present, clone, obs)?
+                    .modify_present(val, cmp, f, (lower, Some(value)), present, clone, obs),
+                Ordering::Greater => {
+                    Self::make_mut_present(right, val, cmp, &mut present, clone, obs)?
+                        .modify_present(val, cmp, f, (Some(value), upper), present, clone, obs)
+                }
+                Ordering::Equal => {
+                    f(value);
+                    let value = &*value;
+                    let lt = |a: &T, b: &T| cmp.compare(a, b) == Ordering::Less;
+                    let in_order = lower.is_none_or(|lower| lt(lower, value))
+                        && upper.is_none_or(|upper| lt(value, upper))
+                        && left.max_value().is_none_or(|max| lt(max, value))
+                        && right.min_value().is_none_or(|min| lt(value, min));
+                    if !in_order {
+                        return Some(self.take_root(clone, obs));
+                    }
+                    Some(None)
+                }
+            },
+        };
+
+        match res {
+            // The value stayed in place, so only what the nodes cache about it needs updating
+            Some(None) => self.update_depth(),
+            Some(Some(_)) => {
+                self.update_depth_observed(obs);
+                self.rebalance_tracked(None, clone, obs);
+            }
+            None => {}
+        }
+        res
+    }
+}
+
+/// Set operations, which take trees apart and copy the nodes they share with others.
+impl<T, K: SharedKind, A: Augment<T>> Tree<T, K, A>
+where
+    T: Clone,
+{
+    /// Helper to take the subtrees and value of the root node apart.
+    pub(crate) fn into_parts(self) -> Option<(Self, T, Self)> {
+        match self {
+            Self::Empty => None,
+            Self::Node {
+                left, value, right, ..
+            } => Some((
+                left.into_tree(T::clone, &mut ()),
+                value,
+                right.into_tree(T::clone, &mut ()),
+            )),
+        }
+    }
+
+    /// Joins two trees and a value ordered between them into a balanced tree.
+    ///
+    /// This takes time proportional to the difference between the depths of the trees.
+    pub(crate) fn join(left: Self, value: T, right: Self) -> Self {
+        if left.depth() > right.depth() + 1 {
+            let (l_left, l_value, l_right) = left.into_parts().expect("left tree is not empty");
+            let mut tree = Self::node(l_left, l_value, Self::join(l_right, value, right));
+            tree.rebalance(T::clone);
+            tree
+        } else if right.depth() > left.depth() + 1 {
+            let (r_left, r_value, r_right) = right.into_parts().expect("right tree is not empty");
+            let mut tree = Self::node(Self::join(left, value, r_left), r_value, r_right);
+            tree.rebalance(T::clone);
+            tree
+        } else {
+            Self::node(left, value, right)
+        }
+    }
+
+    /// Joins two trees, all the values of `left` being ordered before those of `right`.
+    pub(crate) fn join_pair(mut left: Self, right: Self) -> Self {
+        match left.take_max(T::clone, &mut ()) {
+            Some(max) => Self::join(left, max, right),
+            None => right,
+        }
+    }
+
+    /// Splits the tree into the values ordered before `val`, the value equal to it if any, and
+    /// the values ordered after it.
+    pub(crate) fn split<C>(self, val: &T, cmp: &C) -> (Self, Option<T>, Self)
+    where
+        C: Compare<T>,
+    {
+        let Some((left, value, right)) = self.into_parts() else {
+            return (Self::Empty, None, Self::Empty);
+        };
+        match cmp.compare(val, &value) {
+            Ordering::Equal => (left, Some(value), right),
+            Ordering::Less => {
+                let (l_left, found, l_right) = left.split(val, cmp);
+                (l_left, found, Self::join(l_right, value, right))
+            }
+            Ordering::Greater => {
+                let (r_left, found, r_right) = right.split(val, cmp);
+                (Self::join(left, value, r_left), found, r_right)
+            }
+        }
+    }
+
+    /// Merges the values of two trees, keeping those of `self` when both have equal ones.
+    pub(crate) fn union<C>(self, other: Self, cmp: &C) -> Self
+    where
+        C: Compare<T>,
+    {
+        let Some((left, value, right)) = self.into_parts() else {
+            return other;
+        };
+        let (o_left, _, o_right) = other.split(&value, cmp);
+        Self::join(left.union(o_left, cmp), value, right.union(o_right, cmp))
+    }
+
+    /// Keeps the values of `self` which `other` has equal ones to.
+    pub(crate) fn intersection<C>(self, other: Self, cmp: &C) -> Self
+    where
+        C: Compare<T>,
+    {
+        let Some((left, value, right)) = self.into_parts() else {
+            return Self::Empty;
+        };
+        let (o_left, found, o_right) = other.split(&value, cmp);
+        let left = left.intersection(o_left, cmp);
+        let right = right.intersection(o_right, cmp);
+        match found {
+            Some(_) => Self::join(left, value, right),
+            None => Self::join_pair(left, right),
+        }
+    }
+}
+
+/// Cloning a tree copies all its nodes, unlike cloning a [`BST`](crate::BST), which shares them.
+impl<T, K: SharedKind, A: Clone> Clone for Tree<T, K, A>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Node {
+                left,
+                value,
+                right,
+                depth,
+                aug,
+            } => Self::Node {
+                left: Subtree::new(Tree::clone(left)),
+                value: value.clone(),
+                right: Subtree::new(Tree::clone(right)),
+                depth: *depth,
+                aug: aug.clone(),
+            },
+        }
+    }
+}
+
+impl<T, K: SharedKind, A> PartialEq for Tree<T, K, A>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Empty, Self::Empty) => true,
+            (
+                Self::Node {
+                    left: l1,
+                    value: v1,
+                    right: r1,
+                    ..
+                },
+                Self::Node {
+                    left: l2,
+                    value: v2,
+                    right: r2,
+                    ..
+                },
+            ) => v1 == v2 && **l1 == **l2 && **r1 == **r2,
+            _ => false,
+        }
+    }
+}
+
+/// Prints the structure of the tree, as [`TreeView`] does.
+impl<T> fmt::Debug for Tree<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        TreeView::new(self).fmt(f)
+    }
+}
@@ -0,0 +1,305 @@
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{borrow::Borrow, cmp::Ordering, fmt};
+
+use crate::{
+    BST, Tree,
+    compare::{Compare, Natural},
+};
+
+/// A [`BST`] keeping a history of committed versions, which can be queried and compared.
+///
+/// Changes are made to a working tree, and [`VersionedBST::commit`] records its current state
+/// as a new version. Versions share all the nodes they have in common, so committing takes
+/// constant time, and comparing two versions skips the subtrees they share.
+///
+/// # Examples
+///
+/// ```
+/// use bst::VersionedBST;
+///
+/// let mut tree = VersionedBST::new();
+/// tree.insert(1);
+/// tree.insert(2);
+/// let v1 = tree.commit();
+/// tree.remove(&1);
+/// tree.insert(3);
+/// let v2 = tree.commit();
+///
+/// assert!(tree.at_version(v1).unwrap().contains(&1));
+/// assert!(!tree.at_version(v2).unwrap().contains(&1));
+///
+/// let diff = tree.diff(v1, v2).unwrap();
+/// assert_eq!(diff.added, [&3]);
+/// assert_eq!(diff.removed, [&1]);
+/// ```
+pub struct VersionedBST<T, C = Natural> {
+    current: BST<T, C>,
+    /// The committed versions still kept, the oldest first.
+    versions: VecDeque<BST<T, C>>,
+    /// The number of the oldest version kept.
+    first_version: usize,
+    /// The maximum number of versions to keep, if any.
+    limit: Option<usize>,
+}
+
+impl<T, C> Default for VersionedBST<T, C>
+where
+    C: Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T> VersionedBST<T> {
+    /// Creates an empty tree, whose empty state is committed as version 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, C> VersionedBST<T, C> {
+    /// Creates an empty tree ordering its values with the given comparator, whose empty state
+    /// is committed as version 0.
+    pub fn with_comparator(cmp: C) -> Self
+    where
+        C: Clone,
+    {
+        Self {
+            current: BST::with_comparator(cmp.clone()),
+            versions: VecDeque::from([BST::with_comparator(cmp)]),
+            first_version: 0,
+            limit: None,
+        }
+    }
+
+    /// Returns the working tree, with the changes made since the last commit.
+    pub fn current(&self) -> &BST<T, C> {
+        &self.current
+    }
+
+    /// Returns the number of the latest committed version.
+    pub fn latest_version(&self) -> usize {
+        self.first_version + self.versions.len() - 1
+    }
+
+    /// Returns the number of the oldest version still kept.
+    pub fn oldest_version(&self) -> usize {
+        self.first_version
+    }
+
+    /// Only keeps the `count` latest versions from now on, forgetting older ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::VersionedBST;
+    ///
+    /// let mut tree = VersionedBST::new();
+    /// tree.keep_last(2);
+    /// for i in 0..5 {
+    ///     tree.insert(i);
+    ///     tree.commit();
+    /// }
+    /// assert_eq!(tree.oldest_version(), 4);
+    /// assert!(tree.at_version(3).is_none());
+    /// assert_eq!(tree.at_version(4).unwrap().count_nodes(), 4);
+    /// ```
+    pub fn keep_last(&mut self, count: usize) {
+        assert!(count > 0, "at least one version must be kept");
+        self.limit = Some(count);
+        self.forget_old_versions();
+    }
+
+    /// Returns the tree as it was at the given version, or [`None`] if the version isn't kept
+    /// or wasn't committed yet.
+    pub fn at_version(&self, version: usize) -> Option<&BST<T, C>> {
+        self.versions.get(version.checked_sub(self.first_version)?)
+    }
+
+    /// Inserts a value into the working tree.
+    pub fn insert(&mut self, val: T)
+    where
+        C: Compare<T>,
+    {
+        self.current.insert(val);
+    }
+
+    /// Removes a value from the working tree.
+    pub fn remove<Q>(&mut self, val: &Q)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Compare<T>,
+    {
+        self.current.remove(val);
+    }
+
+    /// Clears the working tree, removing all values.
+    pub fn clear(&mut self) {
+        self.current.clear();
+    }
+
+    /// Records the working tree as a new version, returning its number.
+    ///
+    /// This takes constant time, as the version shares its nodes with the working tree.
+    pub fn commit(&mut self) -> usize
+    where
+        T: Clone,
+        C: Clone,
+    {
+        self.versions.push_back(self.current.clone());
+        self.forget_old_versions();
+        self.latest_version()
+    }
+
+    /// Computes the values added and removed between the versions `from` and `to`, or returns
+    /// [`None`] if either version isn't kept.
+    ///
+    /// Subtrees both versions share are skipped, so this takes time depending on how much
+    /// changed between them rather than on their size. Values equal by the comparator are
+    /// considered unchanged.
+    pub fn diff(&self, from: usize, to: usize) -> Option<Diff<'_, T>>
+    where
+        C: Compare<T>,
+    {
+        let from = self.at_version(from)?;
+        let to = self.at_version(to)?;
+        Some(Diff::between(from.root(), to.root(), from.comparator()))
+    }
+
+    fn forget_old_versions(&mut self) {
+        while self.limit.is_some_and(|limit| self.versions.len() > limit) {
+            self.versions.pop_front();
+            self.first_version += 1;
+        }
+    }
+}
+
+/// The values added and removed between two versions of a [`VersionedBST`], in order.
+///
+/// Created by [`VersionedBST::diff`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Diff<'a, T> {
+    /// The values of the newer version missing from the older one.
+    pub added: Vec<&'a T>,
+    /// The values of the older version missing from the newer one.
+    pub removed: Vec<&'a T>,
+}
+
+/// What an in-order walk of a tree still has to visit, the next one last.
+enum Pending<'a, T> {
+    Subtree(&'a Tree<T>),
+    Value(&'a T),
+}
+
+impl<'a, T> Diff<'a, T> {
+    fn between<C>(from: &'a Tree<T>, to: &'a Tree<T>, cmp: &C) -> Self
+    where
+        C: Compare<T>,
+    {
+        let mut diff = Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+        let mut old = Vec::from([Pending::Subtree(from)]);
+        let mut new = Vec::from([Pending::Subtree(to)]);
+
+        // Splits the subtree on top of a walk into its left subtree, value and right subtree
+        fn expand<'a, T>(walk: &mut Vec<Pending<'a, T>>) {
+            if let Some(Pending::Subtree(tree)) = walk.pop()
+                && let Tree::Node {
+                    left, value, right, ..
+                } = tree
+            {
+                walk.push(Pending::Subtree(right));
+                walk.push(Pending::Value(value));
+                walk.push(Pending::Subtree(left));
+            }
+        }
+
+        loop {
+            match (old.last(), new.last()) {
+                (None, None) => return diff,
+                (Some(Pending::Subtree(a)), Some(Pending::Subtree(b))) => {
+                    if core::ptr::eq(*a, *b) {
+                        old.pop();
+                        new.pop();
+                    } else if a.depth() >= b.depth() {
+                        // Walk down the taller subtree, to reach the ones both sides share
+                        expand(&mut old);
+                    } else {
+                        expand(&mut new);
+                    }
+                }
+                (Some(Pending::Subtree(_)), _) => expand(&mut old),
+                (_, Some(Pending::Subtree(_))) => expand(&mut new),
+                (Some(&Pending::Value(a)), Some(&Pending::Value(b))) => match cmp.compare(a, b) {
+                    Ordering::Less => {
+                        diff.removed.push(a);
+                        old.pop();
+                    }
+                    Ordering::Greater => {
+                        diff.added.push(b);
+                        new.pop();
+                    }
+                    Ordering::Equal => {
+                        old.pop();
+                        new.pop();
+                    }
+                },
+                (Some(&Pending::Value(a)), None) => {
+                    diff.removed.push(a);
+                    old.pop();
+                }
+                (None, Some(&Pending::Value(b))) => {
+                    diff.added.push(b);
+                    new.pop();
+                }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Diff<'_, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Diff")
+            .field("added", &self.added)
+            .field("removed", &self.removed)
+            .finish()
+    }
+}
+
+impl<T, C> Clone for VersionedBST<T, C>
+where
+    T: Clone,
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            current: self.current.clone(),
+            versions: self.versions.clone(),
+            first_version: self.first_version,
+            limit: self.limit,
+        }
+    }
+}
+
+impl<T, C> fmt::Debug for VersionedBST<T, C>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VersionedBST")
+            .field("current", &self.current)
+            .field("latest_version", &self.latest_version())
+            .finish()
+    }
+}
@@ -0,0 +1,147 @@
+//! Walks over the structure of a tree, for exporters, validators and debugging tools.
+//!
+//! [`BST::walk`] goes through the tree depth first, calling the [`Visitor`] on entering each
+//! node, on each empty subtree below it and on leaving it. The visitor can skip the subtrees of
+//! a node or stop the walk at any point.
+
+use core::ops::ControlFlow;
+
+use crate::{BST, Tree, tree::Side};
+
+/// Where a subtree sits in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// The number of nodes above the subtree, 0 at the root.
+    pub depth: usize,
+    /// The depth of the left subtree minus that of the right one, 0 for an empty subtree.
+    pub balance_factor: isize,
+    /// Which child of its parent the subtree is, `None` at the root.
+    pub side: Option<Side>,
+}
+
+/// How to carry on after entering a node, other than visiting its subtrees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Walk {
+    /// Skip the subtrees of the node, then leave it.
+    Prune,
+    /// End the walk right away.
+    Stop,
+}
+
+/// The callbacks of a walk over a tree with [`BST::walk`].
+///
+/// Every callback does nothing and carries on by default.
+///
+/// # Examples
+///
+/// ```
+/// use bst::{BST, Visitor, visit::{NodeInfo, Walk}};
+/// use std::ops::ControlFlow;
+///
+/// /// Lists the values with their depth, as an indented outline.
+/// struct Outline(String);
+///
+/// impl Visitor<i32> for Outline {
+///     fn enter_node(&mut self, value: &i32, info: NodeInfo) -> ControlFlow<Walk> {
+///         self.0 += &format!("{}{value}\n", "  ".repeat(info.depth));
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let tree: BST<_> = BST::from_sorted(vec![1, 2, 3]);
+/// let mut outline = Outline(String::new());
+/// tree.walk(&mut outline);
+/// assert_eq!(outline.0, "2\n  1\n  3\n");
+/// ```
+pub trait Visitor<T> {
+    /// Called on entering a node, before its subtrees.
+    ///
+    /// Breaking with [`Walk::Prune`] skips the subtrees of the node, which is still left.
+    fn enter_node(&mut self, _value: &T, _info: NodeInfo) -> ControlFlow<Walk> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called on leaving a node, after its subtrees.
+    fn leave_node(&mut self, _value: &T, _info: NodeInfo) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called on an empty subtree, including the root of an empty tree.
+    fn visit_empty(&mut self, _info: NodeInfo) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Helper function to walk a subtree, breaking if the visitor stopped.
+fn walk<T, V>(tree: &Tree<T>, info: NodeInfo, visitor: &mut V) -> ControlFlow<()>
+where
+    V: Visitor<T> + ?Sized,
+{
+    let Tree::Node {
+        left, value, right, ..
+    } = tree
+    else {
+        return visitor.visit_empty(info);
+    };
+    match visitor.enter_node(value, info) {
+        ControlFlow::Continue(()) => {
+            for (child, side) in [(left, Side::Left), (right, Side::Right)] {
+                let info = NodeInfo {
+                    depth: info.depth + 1,
+                    balance_factor: child.balance_factor(),
+                    side: Some(side),
+                };
+                walk(child, info, visitor)?;
+            }
+        }
+        ControlFlow::Break(Walk::Prune) => (),
+        ControlFlow::Break(Walk::Stop) => return ControlFlow::Break(()),
+    }
+    visitor.leave_node(value, info)
+}
+
+impl<T, C> BST<T, C> {
+    /// Walks through the tree depth first, calling `visitor` on entering each node, on each of
+    /// its empty subtrees and on leaving it.
+    ///
+    /// Returns [`ControlFlow::Break`] if the visitor stopped the walk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bst::{BST, Visitor, visit::{NodeInfo, Walk}};
+    /// use std::ops::ControlFlow;
+    ///
+    /// /// Finds the first node out of balance, as a validator would.
+    /// struct Unbalanced(Option<i32>);
+    ///
+    /// impl Visitor<i32> for Unbalanced {
+    ///     fn enter_node(&mut self, value: &i32, info: NodeInfo) -> ControlFlow<Walk> {
+    ///         if info.balance_factor.abs() > 1 {
+    ///             self.0 = Some(*value);
+    ///             return ControlFlow::Break(Walk::Stop);
+    ///         }
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// }
+    ///
+    /// let mut tree = BST::new();
+    /// for i in 0..100 {
+    ///     tree.insert(i);
+    /// }
+    /// let mut visitor = Unbalanced(None);
+    /// assert!(tree.walk(&mut visitor).is_continue());
+    /// assert_eq!(visitor.0, None);
+    /// ```
+    pub fn walk<V>(&self, visitor: &mut V) -> ControlFlow<()>
+    where
+        V: Visitor<T> + ?Sized,
+    {
+        let info = NodeInfo {
+            depth: 0,
+            balance_factor: self.root.balance_factor(),
+            side: None,
+        };
+        walk(&self.root, info, visitor)
+    }
+}